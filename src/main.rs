@@ -1,6 +1,7 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
+    io::IsTerminal,
     path::{Path, PathBuf},
     process::Command,
 };
@@ -32,12 +33,18 @@ impl Cli {
             Commands::Branch(args) => handle_branch(args),
             Commands::Bc(args) => handle_branch_create(args),
             Commands::Bd(args) => handle_branch_delete(args),
+            Commands::Co(args) => handle_checkout(args),
             Commands::Log(args) => handle_log(args),
             Commands::Up(args) => handle_up(args),
             Commands::Down(args) => handle_down(args),
-            Commands::Top => handle_top(),
-            Commands::Bottom => handle_bottom(),
+            Commands::Top(args) => handle_top(args),
+            Commands::Bottom(args) => handle_bottom(args),
+            Commands::Trunk(args) => handle_trunk(args),
             Commands::Commit(args) => handle_commit(args),
+            Commands::Amend(args) => handle_amend(args),
+            Commands::Absorb(args) => handle_absorb(args),
+            Commands::Move(args) => handle_move(args),
+            Commands::Autosquash(args) => handle_autosquash(args),
             Commands::Sync(args) => handle_sync(args),
             Commands::Restack(args) => handle_restack(args),
         }
@@ -56,6 +63,9 @@ enum Commands {
     /// Delete a branch from the stack (alias for 'branch delete')
     #[command(name = "bd")]
     Bd(BranchDeleteArgs),
+    /// Checkout a branch in the stack (alias for 'branch checkout')
+    #[command(name = "co")]
+    Co(BranchCheckoutArgs),
     /// Show the tracked stacks in ASCII form
     #[command(name = "log", alias = "l")]
     Log(LogArgs),
@@ -66,12 +76,23 @@ enum Commands {
     #[command(alias = "d")]
     Down(DownArgs),
     /// Navigate to the topmost branch in the current stack
-    Top,
+    Top(TopArgs),
     /// Navigate to the bottom of the current stack (just above main)
-    Bottom,
+    Bottom(CheckoutOptions),
+    /// Navigate to the configured main branch
+    Trunk(CheckoutOptions),
     /// Create a commit in the current branch
     #[command(alias = "c")]
     Commit(CommitArgs),
+    /// Amend the last commit and restack every descendant branch
+    Amend(AmendArgs),
+    /// Fold staged hunks into the stack commits that last touched those lines
+    Absorb(AbsorbArgs),
+    /// Move commits from one branch in the stack to another
+    #[command(alias = "mv")]
+    Move(MoveArgs),
+    /// Fold fixup! and squash! commits into their targets across the stack
+    Autosquash(AutosquashArgs),
     /// Sync the current branch (and optionally the entire stack)
     #[command(alias = "s")]
     Sync(SyncArgs),
@@ -93,15 +114,36 @@ enum BranchCommands {
     /// Delete a branch from the stack
     #[command(alias = "d")]
     Delete(BranchDeleteArgs),
+    /// Checkout a branch by name, stack position or `-` for the previous branch
+    #[command(alias = "co")]
+    Checkout(BranchCheckoutArgs),
+    /// Freeze a branch so sync and restack never rewrite it
+    Freeze(BranchFreezeArgs),
+    /// Unfreeze a previously frozen branch
+    Unfreeze(BranchFreezeArgs),
 }
 
 #[derive(Args)]
 struct BranchCreateArgs {
-    /// Name of the new branch
-    branch_name: String,
+    /// Name of the new branch (generated from the commit message when omitted)
+    branch_name: Option<String>,
     /// Specify a different base branch (defaults to current branch)
     #[arg(long)]
     base: Option<String>,
+    /// Commit the staged changes on the new branch with this message
+    #[arg(short, long)]
+    message: Option<String>,
+    /// Stage all changes before committing (requires --message)
+    #[arg(short, long, requires = "message")]
+    all: bool,
+    /// Skip the pre-commit and commit-msg hooks (requires --message)
+    #[arg(short = 'n', long = "no-verify", requires = "message")]
+    no_verify: bool,
+    /// Create the commit even if nothing is staged (requires --message)
+    #[arg(long = "allow-empty", requires = "message")]
+    allow_empty: bool,
+    #[command(flatten)]
+    checkout: CheckoutOptions,
 }
 
 #[derive(Args)]
@@ -111,6 +153,27 @@ struct BranchDeleteArgs {
     /// Force delete even with unmerged changes
     #[arg(long)]
     force: bool,
+    /// Also delete the branch on the configured remote
+    #[arg(long, conflicts_with = "no_remote")]
+    remote: bool,
+    /// Keep the remote branch even if `branch.delete_remote` is enabled
+    #[arg(long = "no-remote")]
+    no_remote: bool,
+}
+
+#[derive(Args)]
+struct BranchCheckoutArgs {
+    /// Branch name, position from the bottom of the current stack (1-based),
+    /// or `-` for the branch last visited through pk
+    target: String,
+    #[command(flatten)]
+    checkout: CheckoutOptions,
+}
+
+#[derive(Args)]
+struct BranchFreezeArgs {
+    /// Name of the branch (defaults to the current branch)
+    branch_name: Option<String>,
 }
 
 #[derive(Args)]
@@ -127,12 +190,42 @@ struct LogArgs {
 struct UpArgs {
     /// Number of branches to move up the stack (towards children, default: 1)
     count: Option<usize>,
+    /// Child to take at a fork, by number or name (repeat for each fork)
+    #[arg(long = "pick", value_name = "N|NAME")]
+    picks: Vec<String>,
+    #[command(flatten)]
+    checkout: CheckoutOptions,
+}
+
+#[derive(Args)]
+struct TopArgs {
+    /// Leaf branch to navigate to when the stack forks
+    #[arg(long, conflicts_with = "deepest")]
+    leaf: Option<String>,
+    /// Navigate to the leaf furthest from the current branch
+    #[arg(long)]
+    deepest: bool,
+    #[command(flatten)]
+    checkout: CheckoutOptions,
 }
 
 #[derive(Args)]
 struct DownArgs {
     /// Number of branches to move down the stack (towards parents, default: 1)
     count: Option<usize>,
+    #[command(flatten)]
+    checkout: CheckoutOptions,
+}
+
+/// How to treat uncommitted changes when switching branches.
+#[derive(Args)]
+struct CheckoutOptions {
+    /// Stash uncommitted changes before switching and re-apply them afterwards
+    #[arg(long, conflicts_with = "force")]
+    autostash: bool,
+    /// Discard uncommitted changes that conflict with the target branch
+    #[arg(long)]
+    force: bool,
 }
 
 #[derive(Args)]
@@ -150,15 +243,149 @@ struct InitArgs {
 
 #[derive(Args)]
 struct CommitArgs {
-    /// Commit message
+    /// Commit message (opens the editor when omitted)
     #[arg(short, long)]
     message: Option<String>,
     /// Stage all changes before committing
     #[arg(short, long)]
     all: bool,
+    /// Interactively choose hunks to stage before committing (like `git add -p`)
+    #[arg(short, long, conflicts_with = "all")]
+    patch: bool,
+    /// Commit only these paths, leaving other staged changes staged
+    #[arg(conflicts_with_all = ["all", "amend"])]
+    paths: Vec<PathBuf>,
     /// Amend the last commit
     #[arg(long)]
     amend: bool,
+    /// Create the commit even if it records no changes
+    #[arg(long, conflicts_with = "amend")]
+    allow_empty: bool,
+    /// Create a `fixup!` commit for a commit lower in the stack (see `pk autosquash`)
+    #[arg(long, value_name = "COMMIT", conflicts_with_all = ["message", "amend", "squash"])]
+    fixup: Option<String>,
+    /// Create a `squash!` commit whose message is added to the target's (see `pk autosquash`)
+    #[arg(long, value_name = "COMMIT", conflicts_with = "amend")]
+    squash: Option<String>,
+    /// Allow committing to a frozen branch
+    #[arg(long)]
+    force: bool,
+    /// Skip the pre-commit and commit-msg hooks
+    #[arg(short = 'n', long = "no-verify")]
+    no_verify: bool,
+    /// Sign the commit (optionally with the given key), overriding `commit.gpgsign`
+    #[arg(
+        short = 'S',
+        long = "gpg-sign",
+        value_name = "KEYID",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    gpg_sign: Option<String>,
+    /// Do not sign the commit, overriding `commit.gpgsign`
+    #[arg(long = "no-gpg-sign", conflicts_with = "gpg_sign")]
+    no_gpg_sign: bool,
+    /// Add a `Key: value` trailer to the message (`key=value` also works)
+    #[arg(long = "trailer", value_name = "TRAILER")]
+    trailers: Vec<String>,
+    /// Add a `Signed-off-by` trailer for the committer
+    #[arg(short, long)]
+    signoff: bool,
+    /// Credit a co-author with a `Co-authored-by` trailer
+    #[arg(long = "co-author", value_name = "NAME <EMAIL>")]
+    co_authors: Vec<String>,
+    /// Commit onto this lower branch of the stack instead, then restack the branches above it
+    #[arg(long, value_name = "BRANCH", conflicts_with_all = ["amend", "fixup", "squash"])]
+    to: Option<String>,
+    /// Continue restacking after resolving conflicts from `--to`
+    #[arg(long = "continue")]
+    continue_rebase: bool,
+    /// Abort restacking after `--to` (the new commit is kept)
+    #[arg(long)]
+    abort: bool,
+}
+
+#[derive(Args)]
+struct AmendArgs {
+    /// Replace the commit message
+    #[arg(short, long, conflicts_with = "edit")]
+    message: Option<String>,
+    /// Edit the commit message in the editor (the message is kept by default)
+    #[arg(short, long)]
+    edit: bool,
+    /// Stage all changes before amending
+    #[arg(short, long)]
+    all: bool,
+    /// Allow amending a frozen branch
+    #[arg(long)]
+    force: bool,
+    /// Skip the pre-commit and commit-msg hooks
+    #[arg(short = 'n', long = "no-verify")]
+    no_verify: bool,
+    /// Sign the commit (optionally with the given key), overriding `commit.gpgsign`
+    #[arg(
+        short = 'S',
+        long = "gpg-sign",
+        value_name = "KEYID",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = ""
+    )]
+    gpg_sign: Option<String>,
+    /// Do not sign the commit, overriding `commit.gpgsign`
+    #[arg(long = "no-gpg-sign", conflicts_with = "gpg_sign")]
+    no_gpg_sign: bool,
+    /// Continue restacking descendants after resolving conflicts
+    #[arg(long = "continue")]
+    continue_rebase: bool,
+    /// Abort restacking descendants (the amended commit is kept)
+    #[arg(long)]
+    abort: bool,
+}
+
+#[derive(Args)]
+struct AbsorbArgs {
+    /// Stage all changes to tracked files before absorbing
+    #[arg(short, long)]
+    all: bool,
+    /// Create `fixup!` commits on the owning branches instead of rewriting commits
+    #[arg(long)]
+    fixup: bool,
+    /// Continue restacking branches after resolving conflicts
+    #[arg(long = "continue")]
+    continue_rebase: bool,
+    /// Abort restacking branches (the absorbed commits are kept)
+    #[arg(long)]
+    abort: bool,
+}
+
+#[derive(Args)]
+struct MoveArgs {
+    /// Commits to move (default: choose interactively)
+    commits: Vec<String>,
+    /// Branch to take the commits from (default: current branch)
+    #[arg(long)]
+    from: Option<String>,
+    /// Branch to move the commits onto
+    #[arg(long)]
+    to: Option<String>,
+    /// Continue restacking branches after resolving conflicts
+    #[arg(long = "continue")]
+    continue_rebase: bool,
+    /// Abort restacking branches (the moved commits stay moved)
+    #[arg(long)]
+    abort: bool,
+}
+
+#[derive(Args)]
+struct AutosquashArgs {
+    /// Continue restacking branches after resolving conflicts
+    #[arg(long = "continue")]
+    continue_rebase: bool,
+    /// Abort restacking branches (the folded commits are kept)
+    #[arg(long)]
+    abort: bool,
 }
 
 #[derive(Args)]
@@ -169,6 +396,18 @@ struct SyncArgs {
     /// Treat the configured main branch as the sync base (implies --all)
     #[arg(long = "from-main")]
     from_main: bool,
+    /// Delete branches found to be merged into the main branch without asking
+    #[arg(long)]
+    delete_merged: bool,
+    /// Fetch the remote and fast-forward the main branch first (implies --all)
+    #[arg(long, conflicts_with = "no_fetch")]
+    fetch: bool,
+    /// Skip fetching even if `sync.fetch` is enabled
+    #[arg(long = "no-fetch")]
+    no_fetch: bool,
+    /// Show how each branch would rebase without changing anything
+    #[arg(long = "dry-run", conflicts_with_all = ["continue_rebase", "abort", "fetch"])]
+    dry_run: bool,
     /// Continue an in-progress sync after resolving conflicts
     #[arg(long = "continue")]
     continue_rebase: bool,
@@ -179,6 +418,9 @@ struct SyncArgs {
 
 #[derive(Args)]
 struct RestackArgs {
+    /// Show how each branch would rebase without changing anything
+    #[arg(long = "dry-run", conflicts_with_all = ["continue_rebase", "abort"])]
+    dry_run: bool,
     /// Continue an in-progress restack after resolving conflicts
     #[arg(long = "continue")]
     continue_rebase: bool,
@@ -236,7 +478,64 @@ fn handle_branch(args: BranchArgs) -> Result<()> {
     match args.command {
         BranchCommands::Create(create_args) => handle_branch_create(create_args),
         BranchCommands::Delete(delete_args) => handle_branch_delete(delete_args),
+        BranchCommands::Checkout(checkout_args) => handle_checkout(checkout_args),
+        BranchCommands::Freeze(freeze_args) => handle_branch_freeze(freeze_args, true),
+        BranchCommands::Unfreeze(freeze_args) => handle_branch_freeze(freeze_args, false),
+    }
+}
+
+fn handle_branch_freeze(args: BranchFreezeArgs, frozen: bool) -> Result<()> {
+    let command = if frozen { "freeze" } else { "unfreeze" };
+    let repo = Repository::discover(".")
+        .with_context(|| format!("`pk branch {}` must be run inside a Git repository", command))?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    // Ensure Pancake is initialized
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    let branch_name = match args.branch_name {
+        Some(name) => name,
+        None => {
+            let head = repo.head().context("unable to resolve current HEAD")?;
+            if !head.is_branch() {
+                bail!("HEAD is not currently on a branch");
+            }
+            head.shorthand()
+                .ok_or_else(|| anyhow!("unable to get current branch name"))?
+                .to_string()
+        }
+    };
+
+    let mut metadata = StackMetadata::load(&repo_root)?;
+    let branch = metadata
+        .branches
+        .get_mut(&branch_name)
+        .ok_or_else(|| anyhow!("Branch '{}' is not tracked by Pancake", branch_name))?;
+
+    if branch.frozen == frozen {
+        println!("Branch '{}' is already {}d", branch_name, command);
+        return Ok(());
+    }
+
+    branch.frozen = frozen;
+    metadata.save(&repo_root)?;
+
+    if frozen {
+        println!(
+            "Froze branch '{}'; sync and restack will leave it untouched",
+            branch_name
+        );
+    } else {
+        println!("Unfroze branch '{}'", branch_name);
     }
+
+    Ok(())
 }
 
 fn handle_log(args: LogArgs) -> Result<()> {
@@ -258,7 +557,7 @@ fn handle_log(args: LogArgs) -> Result<()> {
         return Ok(());
     }
 
-    let forest = build_stack_forest(&metadata);
+    let forest = build_stack_forest(&repo, &metadata);
     if args.short {
         render_short_view(&forest);
     } else {
@@ -301,6 +600,7 @@ fn handle_branch_delete(args: BranchDeleteArgs) -> Result<()> {
 
     // Load stack metadata
     let mut metadata = StackMetadata::load(&repo_root)?;
+    let config = PancakeConfig::load(&repo_root)?;
 
     // Get the parent of the branch being deleted
     let parent = metadata
@@ -308,6 +608,68 @@ fn handle_branch_delete(args: BranchDeleteArgs) -> Result<()> {
         .get(&args.branch_name)
         .and_then(|m| m.parent.clone());
 
+    let mut branch = repo
+        .find_branch(&args.branch_name, BranchType::Local)
+        .with_context(|| format!("unable to find branch '{}'", args.branch_name))?;
+    let tip = branch
+        .get()
+        .peel_to_commit()
+        .with_context(|| format!("unable to get commit for branch '{}'", args.branch_name))?
+        .id();
+
+    // Collect the tips the branch may already be merged into: its parent,
+    // the configured main branch and its upstream.
+    let mut merge_targets = Vec::new();
+    for candidate in parent.iter().chain(std::iter::once(&config.repository.main_branch)) {
+        if candidate == &args.branch_name {
+            continue;
+        }
+        if let Ok(commit) = resolve_branch_commit(&repo, candidate) {
+            merge_targets.push(commit.id());
+        }
+    }
+    let delete_remote = if args.no_remote {
+        false
+    } else {
+        args.remote || config.branch.delete_remote
+    };
+
+    // The upstream only keeps the commits alive if we are not deleting it too
+    if !delete_remote
+        && let Ok(upstream) = branch.upstream()
+        && let Some(oid) = upstream.get().target()
+    {
+        merge_targets.push(oid);
+    }
+
+    let unmerged = unmerged_commits(&repo, tip, &merge_targets)?;
+    if !unmerged.is_empty() {
+        let listing = unmerged
+            .iter()
+            .map(|commit| format!("  {}", describe_commit(commit)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        if !args.force {
+            bail!(
+                "Branch '{}' has {} unmerged commit(s) that would be lost:\n{}\nUse `--force` to delete anyway.",
+                args.branch_name,
+                unmerged.len(),
+                listing
+            );
+        }
+        println!(
+            "Warning: deleting {} unmerged commit(s) from '{}':\n{}",
+            unmerged.len(),
+            args.branch_name,
+            listing
+        );
+    }
+
+    if delete_remote {
+        let remote_branch = resolve_remote_branch(&repo, &args.branch_name, &config)?;
+        delete_remote_branch(&repo, &repo_root, &remote_branch, tip, args.force)?;
+    }
+
     // Get all children of the branch being deleted
     let children = metadata.get_children(&args.branch_name);
 
@@ -317,33 +679,19 @@ fn handle_branch_delete(args: BranchDeleteArgs) -> Result<()> {
         println!("Restacked '{}' onto '{}'", child, parent.as_deref().unwrap_or("main"));
     }
 
-    // Delete the Git branch
-    let mut branch = repo
-        .find_branch(&args.branch_name, BranchType::Local)
-        .with_context(|| format!("unable to find branch '{}'", args.branch_name))?;
+    branch
+        .delete()
+        .with_context(|| format!("failed to delete branch '{}'", args.branch_name))?;
 
-    // Check if the branch is fully merged (unless --force is used)
-    if !args.force {
-        // Try to delete with the unmerged check
-        match branch.delete() {
-            Ok(_) => {},
-            Err(e) => {
-                bail!(
-                    "Branch '{}' has unmerged changes. Use `--force` to delete anyway.\nError: {}",
-                    args.branch_name,
-                    e
-                );
-            }
-        }
-    } else {
-        // Force delete
-        branch.delete()
-            .with_context(|| format!("failed to delete branch '{}'", args.branch_name))?;
-    }
+    // Record the old tip so the branch can be restored later
+    let mut deleted_log = DeletedBranchLog::load(&repo_root)?;
+    deleted_log.record(&args.branch_name, tip, parent.clone());
+    deleted_log.save(&repo_root)?;
 
     // Remove from stack metadata
     metadata.remove_branch(&args.branch_name);
     metadata.save(&repo_root)?;
+    record_change_ids(&repo, &repo_root, &metadata)?;
 
     if children.is_empty() {
         println!("Deleted branch '{}'", args.branch_name);
@@ -354,10 +702,146 @@ fn handle_branch_delete(args: BranchDeleteArgs) -> Result<()> {
             children.len()
         );
     }
+    println!("Restore it with `git branch {} {}`", args.branch_name, tip);
+
+    Ok(())
+}
+
+struct RemoteBranch {
+    remote: String,
+    name: String,
+}
+
+impl RemoteBranch {
+    fn tracking_ref(&self) -> String {
+        format!("refs/remotes/{}/{}", self.remote, self.name)
+    }
+}
+
+/// Resolves the remote counterpart of a local branch, preferring its configured
+/// upstream and falling back to the same name on the Pancake remote.
+fn resolve_remote_branch(
+    repo: &Repository,
+    branch_name: &str,
+    config: &PancakeConfig,
+) -> Result<RemoteBranch> {
+    let git_config = repo.config().context("failed to read git config")?;
+    let remote = git_config
+        .get_string(&format!("branch.{}.remote", branch_name))
+        .ok()
+        .filter(|remote| remote != ".");
+    let merge = git_config
+        .get_string(&format!("branch.{}.merge", branch_name))
+        .ok();
+
+    match (remote, merge) {
+        (Some(remote), Some(merge)) => Ok(RemoteBranch {
+            remote,
+            name: merge
+                .strip_prefix("refs/heads/")
+                .unwrap_or(&merge)
+                .to_string(),
+        }),
+        _ => Ok(RemoteBranch {
+            remote: config.repository.remote.clone(),
+            name: branch_name.to_string(),
+        }),
+    }
+}
+
+/// Deletes a branch on its remote, refusing unless `force` is set when the
+/// remote holds commits that the local branch at `local_tip` does not.
+fn delete_remote_branch(
+    repo: &Repository,
+    repo_root: &Path,
+    remote_branch: &RemoteBranch,
+    local_tip: git2::Oid,
+    force: bool,
+) -> Result<()> {
+    let remote_ref = format!("refs/heads/{}", remote_branch.name);
+    let output = run_git_command(repo_root, &["ls-remote", &remote_branch.remote, &remote_ref])?;
+    if !output.status.success() {
+        return Err(format_git_error(
+            &["ls-remote", &remote_branch.remote, &remote_ref],
+            &output,
+        ));
+    }
+    let listing = String::from_utf8_lossy(&output.stdout);
+    let actual_tip = listing
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .find(|(_, name)| *name == remote_ref)
+        .map(|(oid, _)| oid.to_string());
+
+    let Some(actual_tip) = actual_tip else {
+        println!(
+            "Branch '{}' does not exist on remote '{}'; nothing to delete there",
+            remote_branch.name, remote_branch.remote
+        );
+        return Ok(());
+    };
+
+    // A fetch moves the remote-tracking ref too, so only the local branch can
+    // vouch for the remote commits
+    let contained = git2::Oid::from_str(&actual_tip).is_ok_and(|remote_tip| {
+        remote_tip == local_tip
+            || (repo.find_commit(remote_tip).is_ok()
+                && repo
+                    .graph_descendant_of(local_tip, remote_tip)
+                    .unwrap_or(false))
+    });
+    if !contained {
+        if !force {
+            bail!(
+                "Remote branch '{}/{}' has commits that are not on the local branch (remote tip {}). Review them before deleting, or use `--force` or `--no-remote`.",
+                remote_branch.remote,
+                remote_branch.name,
+                &actual_tip[..7]
+            );
+        }
+        println!(
+            "Warning: deleting remote commits of '{}/{}' that are not on the local branch (remote tip {})",
+            remote_branch.remote,
+            remote_branch.name,
+            &actual_tip[..7]
+        );
+    }
 
+    let lease = format!("--force-with-lease={}:{}", remote_ref, actual_tip);
+    let refspec = format!(":{}", remote_ref);
+    run_git_checked(repo_root, &["push", &lease, &remote_branch.remote, &refspec])?;
+    println!(
+        "Deleted remote branch '{}/{}'",
+        remote_branch.remote, remote_branch.name
+    );
     Ok(())
 }
 
+/// Returns the commits reachable from `tip` but from none of `targets`, newest first.
+fn unmerged_commits<'repo>(
+    repo: &'repo Repository,
+    tip: git2::Oid,
+    targets: &[git2::Oid],
+) -> Result<Vec<git2::Commit<'repo>>> {
+    let mut revwalk = repo.revwalk().context("failed to walk commit history")?;
+    revwalk.push(tip).context("failed to walk commit history")?;
+    for target in targets {
+        revwalk.hide(*target).context("failed to walk commit history")?;
+    }
+
+    let mut commits = Vec::new();
+    for oid in revwalk {
+        let oid = oid.context("failed to walk commit history")?;
+        commits.push(repo.find_commit(oid).context("failed to find commit")?);
+    }
+    Ok(commits)
+}
+
+fn describe_commit(commit: &git2::Commit) -> String {
+    let id = commit.id().to_string();
+    format!("{} {}", &id[..7], commit.summary().unwrap_or(""))
+}
+
 fn handle_branch_create(args: BranchCreateArgs) -> Result<()> {
     let repo =
         Repository::discover(".").context("`pk branch create` must be run inside a Git repository")?;
@@ -375,8 +859,8 @@ fn handle_branch_create(args: BranchCreateArgs) -> Result<()> {
     // Determine the base branch
     let base_branch = match args.base {
         Some(base) => {
-            // Verify the base branch exists
-            if !branch_exists(&repo, &base) {
+            // Verify the base branch exists, either locally or as a remote-tracking branch
+            if !branch_exists(&repo, &base) && !remote_branch_exists(&repo, &base) {
                 bail!("Base branch '{}' does not exist", base);
             }
             base
@@ -393,41 +877,185 @@ fn handle_branch_create(args: BranchCreateArgs) -> Result<()> {
         }
     };
 
+    let branch_name = match (args.branch_name, args.message.as_deref()) {
+        (Some(name), _) => name,
+        (None, Some(message)) => {
+            let config = PancakeConfig::load(&repo_root)?;
+            generate_branch_name(&config, message)?
+        }
+        (None, None) => bail!("Branch name is required unless `-m <message>` is given"),
+    };
+
     // Check if the new branch already exists
-    if branch_exists(&repo, &args.branch_name) {
-        bail!("Branch '{}' already exists", args.branch_name);
+    if branch_exists(&repo, &branch_name) {
+        bail!("Branch '{}' already exists", branch_name);
     }
 
     // Create the new branch
-    let base_commit = repo
-        .find_branch(&base_branch, BranchType::Local)
-        .with_context(|| format!("unable to find branch '{}'", base_branch))?
-        .get()
-        .peel_to_commit()
-        .with_context(|| format!("unable to get commit for branch '{}'", base_branch))?;
+    let base_commit = resolve_branch_commit(&repo, &base_branch)?;
+
+    if args.message.is_some() {
+        // The staged changes are relative to HEAD, so they can only be committed
+        // onto a branch that starts from the same commit.
+        let head_commit = repo
+            .head()
+            .and_then(|head| head.peel_to_commit())
+            .context("unable to resolve current HEAD")?;
+        if head_commit.id() != base_commit.id() {
+            bail!(
+                "Cannot commit staged changes onto '{}' because it does not point at HEAD. Check it out first.",
+                base_branch
+            );
+        }
+        if args.all {
+            stage_all(&repo)?;
+        }
+    }
 
-    repo.branch(&args.branch_name, &base_commit, false)
-        .with_context(|| format!("failed to create branch '{}'", args.branch_name))?;
+    repo.branch(&branch_name, &base_commit, false)
+        .with_context(|| format!("failed to create branch '{}'", branch_name))?;
+
+    // Checkout the new branch. When committing, HEAD already points at the base
+    // commit, so only HEAD moves and the staged changes are left untouched.
+    let mut committed = None;
+    if let Some(message) = args.message {
+        let previous_branch = repo
+            .head()
+            .ok()
+            .filter(|head| head.is_branch())
+            .and_then(|head| head.shorthand().map(|s| s.to_string()));
+        repo.set_head(&format!("refs/heads/{}", branch_name))
+            .context("failed to set HEAD to new branch")?;
+
+        // The hooks see the new branch checked out, as with `git commit`; if
+        // any step fails, HEAD goes back and the branch is removed again
+        let hooks = CommitHooks::new(&repo, !args.no_verify)?;
+        let commit = commit_on_new_branch(&repo, &repo_root, &hooks, message, args.allow_empty);
+        if let Err(err) = commit {
+            let _ = match &previous_branch {
+                Some(previous) => repo.set_head(&format!("refs/heads/{}", previous)),
+                None => repo.set_head_detached(base_commit.id()),
+            };
+            if let Ok(mut branch) = repo.find_branch(&branch_name, BranchType::Local) {
+                let _ = branch.delete();
+            }
+            return Err(err);
+        }
+        committed = Some(hooks);
 
-    // Checkout the new branch
-    repo.set_head(&format!("refs/heads/{}", args.branch_name))
-        .context("failed to set HEAD to new branch")?;
-    repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
-        .context("failed to checkout new branch")?;
+        if let Some(previous_branch) = previous_branch {
+            let mut navigation = NavigationState::load(&repo_root)?;
+            navigation.record_visit(previous_branch);
+            navigation.save(&repo_root)?;
+        }
+    } else if let Err(err) = checkout_branch(&repo, &branch_name, &args.checkout) {
+        // Leave no half-created branch behind when the switch is refused
+        if let Ok(mut branch) = repo.find_branch(&branch_name, BranchType::Local) {
+            let _ = branch.delete();
+        }
+        return Err(err);
+    }
 
     // Update stack metadata
     let mut metadata = StackMetadata::load(&repo_root)?;
-    metadata.add_branch(args.branch_name.clone(), Some(base_branch.clone()));
+    metadata.add_branch(branch_name.clone(), Some(base_branch.clone()));
     metadata.save(&repo_root)?;
 
     println!(
         "Created branch '{}' based on '{}' and switched to it",
-        args.branch_name, base_branch
+        branch_name, base_branch
     );
 
+    if let Some(hooks) = committed {
+        hooks.post_commit();
+        record_change_ids(&repo, &repo_root, &metadata)?;
+        println!("Created commit on branch '{}'", branch_name);
+    }
+
     Ok(())
 }
 
+/// Commits the index onto the freshly created branch HEAD points at.
+fn commit_on_new_branch(
+    repo: &Repository,
+    repo_root: &Path,
+    hooks: &CommitHooks,
+    message: String,
+    allow_empty: bool,
+) -> Result<git2::Oid> {
+    hooks.pre_commit(repo)?;
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    let tree = repo
+        .index()
+        .and_then(|mut index| index.write_tree())
+        .context("failed to write tree")?;
+    if !allow_empty && tree == head.peel_to_tree().context("failed to get HEAD tree")?.id() {
+        let branch = head.shorthand().unwrap_or("HEAD");
+        return Err(nothing_to_commit(repo, branch, "pk bc")?);
+    }
+    let config = PancakeConfig::load(repo_root)?;
+    let trailers: Vec<_> = change_id_trailer(repo, &config, None)?
+        .into_iter()
+        .collect();
+    let message =
+        compose_commit_message(repo, hooks, CommitMessageSeed::message(message), &trailers)?;
+    let signer = CommitSigner::resolve(repo, None, None)?;
+    commit_tree(repo, &message, tree, signer.as_ref())
+}
+
+/// Builds a branch name from the first line of a commit message using the
+/// `branch.name_template` config.
+fn generate_branch_name(config: &PancakeConfig, message: &str) -> Result<String> {
+    use std::fmt::Write;
+
+    let summary = message.lines().next().unwrap_or("");
+    let slug = slugify(summary);
+    if slug.is_empty() {
+        bail!("Cannot generate a branch name from the commit message; pass a branch name explicitly");
+    }
+
+    // chrono reports invalid format strings only while formatting
+    let mut date = String::new();
+    write!(
+        date,
+        "{}",
+        chrono::Local::now().format(&config.branch.name_date_format)
+    )
+    .map_err(|_| {
+        anyhow!(
+            "invalid `branch.name_date_format` '{}' in .pancake/config",
+            config.branch.name_date_format
+        )
+    })?;
+    let fixed = config
+        .branch
+        .name_template
+        .replace("{prefix}", &config.stack.prefix)
+        .replace("{date}", &date);
+
+    // Truncate only the slug so the prefix and date survive
+    let budget = config
+        .branch
+        .name_max_length
+        .saturating_sub(fixed.replace("{slug}", "").chars().count());
+    let slug: String = slug.chars().take(budget.max(1)).collect();
+    let slug = slug.trim_end_matches('-');
+
+    Ok(fixed.replace("{slug}", slug))
+}
+
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+    for ch in text.chars() {
+        if ch.is_ascii_alphanumeric() {
+            slug.push(ch.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
+
 fn handle_up(args: UpArgs) -> Result<()> {
     let repo = Repository::discover(".").context("`pk up` must be run inside a Git repository")?;
     let workdir = repo
@@ -456,10 +1084,12 @@ fn handle_up(args: UpArgs) -> Result<()> {
 
     // Navigate up (to children) the specified number of times
     let count = args.count.unwrap_or(1);
+    let mut picks = args.picks.iter();
     let mut target = current_branch.clone();
 
     for i in 0..count {
-        let children = metadata.get_children(&target);
+        let mut children = metadata.get_children(&target);
+        children.sort();
 
         if children.is_empty() {
             if i == 0 {
@@ -470,33 +1100,79 @@ fn handle_up(args: UpArgs) -> Result<()> {
         } else if children.len() == 1 {
             target = children[0].clone();
         } else {
-            // Multiple children - need to select one
-            if count > 1 {
-                bail!(
-                    "Branch '{}' has multiple children. Cannot automatically navigate up {} branches.",
-                    target,
-                    count
-                );
-            }
-
-            println!("Branch '{}' has multiple children. Select one:", target);
-            for (idx, child) in children.iter().enumerate() {
-                println!("  {}: {}", idx + 1, child);
-            }
-
-            // For now, bail with a helpful message
-            // In the future, we could use an interactive selector
-            bail!("Multiple children found. Interactive selection not yet implemented.\nUse `pk checkout <branch-name>` to select a specific branch.");
+            let prompt = format!("Branch '{}' has multiple children. Select one", target);
+            target = pick_branch(
+                &prompt,
+                &children,
+                picks.next().map(String::as_str),
+                0,
+                "`--pick <n|name>`",
+            )?;
         }
     }
 
     // Checkout the target branch
-    checkout_branch(&repo, &target)?;
+    checkout_branch(&repo, &target, &args.checkout)?;
     println!("Switched to branch '{}'", target);
 
     Ok(())
 }
 
+/// Chooses one of `options`, either from an explicit `--pick` value (a 1-based
+/// number or a branch name) or interactively when attached to a terminal.
+/// Without a terminal, the error points at `flag_hint`, the caller's own way
+/// of choosing.
+fn pick_branch(
+    prompt: &str,
+    options: &[String],
+    pick: Option<&str>,
+    default: usize,
+    flag_hint: &str,
+) -> Result<String> {
+    if let Some(pick) = pick {
+        if let Some(name) = options.iter().find(|option| option.as_str() == pick) {
+            return Ok(name.clone());
+        }
+        if let Ok(number) = pick.parse::<usize>()
+            && (1..=options.len()).contains(&number)
+        {
+            return Ok(options[number - 1].clone());
+        }
+        bail!(
+            "'{}' is not one of the choices: {}",
+            pick,
+            options.join(", ")
+        );
+    }
+
+    if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        let items: Vec<String> = options
+            .iter()
+            .enumerate()
+            .map(|(idx, option)| format!("{}: {}", idx + 1, option))
+            .collect();
+        let selection = dialoguer::Select::new()
+            .with_prompt(prompt)
+            .items(&items)
+            .default(default)
+            .interact_opt()
+            .context("failed to read selection")?;
+        return match selection {
+            Some(idx) => Ok(options[idx].clone()),
+            None => bail!("No branch selected"),
+        };
+    }
+
+    println!("{}:", prompt);
+    for (idx, option) in options.iter().enumerate() {
+        println!("  {}: {}", idx + 1, option);
+    }
+    bail!(
+        "Multiple choices found and no terminal is attached.\nUse {} to select one.",
+        flag_hint
+    );
+}
+
 fn handle_down(args: DownArgs) -> Result<()> {
     let repo = Repository::discover(".").context("`pk down` must be run inside a Git repository")?;
     let workdir = repo
@@ -551,13 +1227,13 @@ fn handle_down(args: DownArgs) -> Result<()> {
     }
 
     // Checkout the target branch
-    checkout_branch(&repo, &target)?;
+    checkout_branch(&repo, &target, &args.checkout)?;
     println!("Switched to branch '{}'", target);
 
     Ok(())
 }
 
-fn handle_top() -> Result<()> {
+fn handle_top(args: TopArgs) -> Result<()> {
     let repo = Repository::discover(".").context("`pk top` must be run inside a Git repository")?;
     let workdir = repo
         .workdir()
@@ -588,8 +1264,50 @@ fn handle_top() -> Result<()> {
         bail!("Current branch '{}' is not tracked by Pancake", current_branch);
     }
 
-    // Find the top of the stack
-    let top_branch = metadata.find_stack_top(&current_branch);
+    // Find every top of the stack reachable from here
+    let leaves = metadata.find_stack_leaves(&current_branch);
+    let navigation = NavigationState::load(&repo_root)?;
+
+    let top_branch = if let Some(leaf) = args.leaf {
+        if !leaves.iter().any(|(name, _)| name == &leaf) {
+            bail!(
+                "Branch '{}' is not a top of the stack above '{}'",
+                leaf,
+                current_branch
+            );
+        }
+        leaf
+    } else if args.deepest {
+        // The first leaf wins ties so the result does not depend on map order
+        let deepest = leaves.iter().map(|(_, depth)| *depth).max().unwrap_or(0);
+        leaves
+            .iter()
+            .find(|(_, depth)| *depth == deepest)
+            .map(|(name, _)| name.clone())
+            .unwrap_or_else(|| current_branch.clone())
+    } else if leaves.len() == 1 {
+        leaves[0].0.clone()
+    } else {
+        let remembered = navigation.remembered_top(&metadata, &current_branch);
+        let names: Vec<String> = leaves.iter().map(|(name, _)| name.clone()).collect();
+        let interactive = std::io::stdin().is_terminal() && std::io::stderr().is_terminal();
+        match remembered {
+            Some(leaf) if !interactive => leaf,
+            remembered => {
+                let default = remembered
+                    .and_then(|leaf| names.iter().position(|name| name == &leaf))
+                    .unwrap_or(0);
+                let prompt = format!("The stack above '{}' has multiple tops", current_branch);
+                pick_branch(
+                    &prompt,
+                    &names,
+                    None,
+                    default,
+                    "`--leaf <name>` or `--deepest`",
+                )?
+            }
+        }
+    };
 
     if top_branch == current_branch {
         println!("Already at the top of the stack: '{}'", current_branch);
@@ -597,13 +1315,18 @@ fn handle_top() -> Result<()> {
     }
 
     // Checkout the top branch
-    checkout_branch(&repo, &top_branch)?;
+    checkout_branch(&repo, &top_branch, &args.checkout)?;
     println!("Switched to branch '{}' (top of stack)", top_branch);
 
+    // Reload: the checkout recorded the branch we left
+    let mut navigation = NavigationState::load(&repo_root)?;
+    navigation.remember_path(&metadata, &current_branch, &top_branch);
+    navigation.save(&repo_root)?;
+
     Ok(())
 }
 
-fn handle_bottom() -> Result<()> {
+fn handle_bottom(args: CheckoutOptions) -> Result<()> {
     let repo = Repository::discover(".").context("`pk bottom` must be run inside a Git repository")?;
     let workdir = repo
         .workdir()
@@ -643,14 +1366,14 @@ fn handle_bottom() -> Result<()> {
     }
 
     // Checkout the bottom branch
-    checkout_branch(&repo, &bottom_branch)?;
+    checkout_branch(&repo, &bottom_branch, &args)?;
     println!("Switched to branch '{}' (bottom of stack)", bottom_branch);
 
     Ok(())
 }
 
-fn handle_commit(args: CommitArgs) -> Result<()> {
-    let repo = Repository::discover(".").context("`pk commit` must be run inside a Git repository")?;
+fn handle_checkout(args: BranchCheckoutArgs) -> Result<()> {
+    let repo = Repository::discover(".").context("`pk checkout` must be run inside a Git repository")?;
     let workdir = repo
         .workdir()
         .context("bare repositories are not supported by Pancake")?;
@@ -662,81 +1385,2628 @@ fn handle_commit(args: CommitArgs) -> Result<()> {
         bail!("Pancake is not initialized. Run `pk init` first.");
     }
 
-    // Get current branch
     let head = repo.head().context("unable to resolve current HEAD")?;
-    if !head.is_branch() {
-        bail!("HEAD is not currently on a branch");
-    }
-    let current_branch = head
-        .shorthand()
-        .ok_or_else(|| anyhow!("unable to get current branch name"))?
-        .to_string();
+    let current_branch = if head.is_branch() {
+        head.shorthand().map(|s| s.to_string())
+    } else {
+        None
+    };
 
-    // Get the commit message
-    let message = match args.message {
-        Some(msg) => msg,
-        None => {
-            bail!("Commit message is required. Use `-m <message>` to provide one.");
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    let (target, context) = if args.target == "-" {
+        let navigation = NavigationState::load(&repo_root)?;
+        let previous = navigation
+            .history
+            .last()
+            .cloned()
+            .ok_or_else(|| anyhow!("No previous branch recorded. Navigate with pk first."))?;
+        (previous, None)
+    } else if let Ok(position) = args.target.parse::<usize>()
+        && !branch_exists(&repo, &args.target)
+    {
+        let current_branch = current_branch
+            .clone()
+            .ok_or_else(|| anyhow!("HEAD is not currently on a branch"))?;
+        if !metadata.branches.contains_key(&current_branch) {
+            bail!("Current branch '{}' is not tracked by Pancake", current_branch);
+        }
+        let stack = current_stack_sequence(&metadata, &current_branch);
+        if position == 0 || position > stack.len() {
+            bail!(
+                "Position {} is out of range; the current stack has {} branch(es)",
+                position,
+                stack.len()
+            );
+        }
+        let context = format!("{} of {} in stack", position, stack.len());
+        (stack[position - 1].clone(), Some(context))
+    } else {
+        if !branch_exists(&repo, &args.target) {
+            bail!("Branch '{}' does not exist", args.target);
         }
+        (args.target.clone(), None)
     };
 
-    // Stage changes if --all is specified
-    if args.all {
-        let mut index = repo.index().context("failed to get repository index")?;
-        index.add_all(["."].iter(), git2::IndexAddOption::DEFAULT, None)
-            .context("failed to stage changes")?;
+    if current_branch.as_deref() == Some(target.as_str()) {
+        println!("Already on branch '{}'", target);
+        return Ok(());
+    }
+
+    checkout_branch(&repo, &target, &args.checkout)?;
+    match context {
+        Some(context) => println!("Switched to branch '{}' ({})", target, context),
+        None => println!("Switched to branch '{}'", target),
+    }
+
+    Ok(())
+}
+
+fn handle_trunk(args: CheckoutOptions) -> Result<()> {
+    let repo = Repository::discover(".").context("`pk trunk` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    // Ensure Pancake is initialized
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    let config = PancakeConfig::load(&repo_root)?;
+    let trunk = config.repository.main_branch;
+
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if head.is_branch() && head.shorthand() == Some(trunk.as_str()) {
+        println!("Already on the main branch: '{}'", trunk);
+        return Ok(());
+    }
+
+    if !branch_exists(&repo, &trunk) {
+        bail!("Main branch '{}' does not exist", trunk);
+    }
+
+    checkout_branch(&repo, &trunk, &args)?;
+    println!("Switched to branch '{}'", trunk);
+
+    Ok(())
+}
+
+/// Returns the branches of the current branch's stack, bottom first: its
+/// ancestors plus every descendant, ordered as `collect_branch_sequence` does.
+fn current_stack_sequence(metadata: &StackMetadata, current_branch: &str) -> Vec<String> {
+    let bottom = metadata.find_stack_bottom(current_branch);
+    let mut ancestors = vec![current_branch.to_string()];
+    let mut cursor = current_branch.to_string();
+    while cursor != bottom {
+        match metadata.get_parent(&cursor) {
+            Some(parent) => {
+                ancestors.push(parent.clone());
+                cursor = parent;
+            }
+            None => break,
+        }
+    }
+    let descendants = collect_branch_sequence(metadata, current_branch);
+
+    collect_branch_sequence(metadata, &bottom)
+        .into_iter()
+        .filter(|branch| ancestors.contains(branch) || descendants.contains(branch))
+        .collect()
+}
+
+fn handle_commit(args: CommitArgs) -> Result<()> {
+    let repo = Repository::discover(".").context("`pk commit` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    // Ensure Pancake is initialized
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    if args.continue_rebase && args.abort {
+        bail!("Cannot use --continue and --abort together.");
+    }
+
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    if args.continue_rebase {
+        return continue_operation(&repo, &repo_root, &metadata, OperationKind::Commit);
+    }
+
+    if args.abort {
+        return abort_operation(&repo_root, OperationKind::Commit);
+    }
+
+    // Get current branch
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if !head.is_branch() {
+        bail!("HEAD is not currently on a branch");
+    }
+    let current_branch = head
+        .shorthand()
+        .ok_or_else(|| anyhow!("unable to get current branch name"))?
+        .to_string();
+
+    let to = args.to.as_deref().filter(|target| *target != current_branch);
+    if let Some(target) = to {
+        ensure_no_active_operation(&repo_root)?;
+        let below = std::iter::successors(metadata.get_parent(&current_branch), |branch| {
+            metadata.get_parent(branch)
+        })
+        .take_while(|branch| metadata.branches.contains_key(branch))
+        .any(|branch| branch == target);
+        if !below {
+            bail!(
+                "Branch '{}' is not below '{}' in the stack; `--to` only commits into lower branches.",
+                target,
+                current_branch
+            );
+        }
+    }
+    let commit_branch = to.unwrap_or(&current_branch);
+    if metadata.is_frozen(commit_branch) && !args.force {
+        bail!(
+            "Branch '{}' is frozen. Use `--force` to commit to it anyway, or `pk branch unfreeze`.",
+            commit_branch
+        );
+    }
+
+    // Stage changes if --all is specified
+    if args.all {
+        stage_all(&repo)?;
+    }
+
+    let paths = args
+        .paths
+        .iter()
+        .map(|path| repo_relative_path(workdir, path))
+        .collect::<Result<Vec<_>>>()?;
+    if args.patch {
+        stage_patch(&repo_root, &paths)?;
+        repo.index()
+            .and_then(|mut index| index.read(true))
+            .context("failed to reload repository index")?;
+        if staged_changes(&repo)?.is_empty() {
+            bail!("No changes selected; nothing to commit.");
+        }
+    }
+    // Committing named paths commits their working tree contents only
+    let path_limited = !args.patch && !paths.is_empty();
+    if path_limited {
+        stage_paths(&repo, &paths)?;
+    }
+
+    let hooks = CommitHooks::new(&repo, !args.no_verify)?;
+    hooks.pre_commit(&repo)?;
+
+    // Built after the hook, so whatever it staged or reformatted is included
+    let tree = if path_limited {
+        path_limited_tree(&repo, &paths)?
+    } else {
+        repo.index()
+            .and_then(|mut index| index.write_tree())
+            .context("failed to write tree")?
+    };
+    if !args.amend && !args.allow_empty {
+        let head_tree = head.peel_to_tree().context("failed to get HEAD tree")?;
+        if tree == head_tree.id() {
+            return Err(nothing_to_commit(&repo, &current_branch, "pk commit")?);
+        }
+    }
+
+    let mut trailers = commit_trailers(&repo, &args)?;
+
+    // Get the commit message, asking the editor when none was given
+    let seed = if let Some(rev) = &args.fixup {
+        let target = resolve_fold_target(&repo, &metadata, &current_branch, rev)?;
+        CommitMessageSeed::message(format!("fixup! {}", target.summary().unwrap_or("")))
+    } else if let Some(rev) = &args.squash {
+        let target = resolve_fold_target(&repo, &metadata, &current_branch, rev)?;
+        let subject = format!("squash! {}", target.summary().unwrap_or(""));
+        match args.message {
+            Some(msg) => CommitMessageSeed::message(format!("{}\n\n{}", subject, msg)),
+            None => CommitMessageSeed {
+                contents: build_commit_template(
+                    &repo,
+                    &metadata,
+                    &current_branch,
+                    &format!("{}\n\n", subject),
+                )?,
+                source: "message",
+                commit: None,
+                edit: true,
+            },
+        }
+    } else {
+        match args.message {
+            Some(msg) => CommitMessageSeed::message(msg),
+            None if args.amend => {
+                let head_commit = head.peel_to_commit().context("failed to get HEAD commit")?;
+                let contents = build_commit_template(
+                    &repo,
+                    &metadata,
+                    &current_branch,
+                    head_commit.message().unwrap_or(""),
+                )?;
+                CommitMessageSeed {
+                    contents,
+                    source: "commit",
+                    commit: Some(head_commit.id()),
+                    edit: true,
+                }
+            }
+            None => {
+                let template = read_commit_template(&repo)?;
+                let source = if template.is_some() { "template" } else { "" };
+                let contents = build_commit_template(
+                    &repo,
+                    &metadata,
+                    &current_branch,
+                    template.as_deref().unwrap_or(""),
+                )?;
+                CommitMessageSeed {
+                    contents,
+                    source,
+                    commit: None,
+                    edit: true,
+                }
+            }
+        }
+    };
+    // Fixups are folded away, so only the commits they target carry a Change-Id
+    if args.fixup.is_none() && args.squash.is_none() {
+        let config = PancakeConfig::load(&repo_root)?;
+        let amended = if args.amend {
+            Some(head.peel_to_commit().context("failed to get HEAD commit")?)
+        } else {
+            None
+        };
+        trailers.extend(change_id_trailer(&repo, &config, amended.as_ref())?);
+    }
+    let message = compose_commit_message(&repo, &hooks, seed, &trailers)?;
+    let signer = CommitSigner::resolve(
+        &repo,
+        signing_override(args.gpg_sign.as_deref(), args.no_gpg_sign),
+        args.gpg_sign.as_deref(),
+    )?;
+
+    if let Some(target) = to {
+        let mut state = commit_to_branch(
+            &repo,
+            &metadata,
+            target,
+            &current_branch,
+            tree,
+            &message,
+            signer.as_ref(),
+        )?;
+        hooks.post_commit();
+        record_change_ids(&repo, &repo_root, &metadata)?;
+        println!("Created commit on branch '{}'", target);
+
+        // The committed changes come back to this branch through the restack
+        if has_uncommitted_changes(&repo)? {
+            run_git_checked(&repo_root, &["stash", "push", "-m", "pk commit"])?;
+            state.autostash = true;
+        }
+        return execute_operation(&repo, &repo_root, &metadata, state);
+    }
+
+    if args.amend {
+        warn_on_noop_amend(&repo, &message)?;
+        amend_head(&repo, &message, signer.as_ref())?;
+        hooks.post_commit();
+        println!("Amended commit on branch '{}'", current_branch);
+    } else {
+        commit_tree(&repo, &message, tree, signer.as_ref())?;
+        hooks.post_commit();
+        println!("Created commit on branch '{}'", current_branch);
+    }
+    record_change_ids(&repo, &repo_root, &metadata)?;
+
+    Ok(())
+}
+
+/// Builds the initial contents of the commit message editor: `initial_message`
+/// (`commit.template` or the message being amended) followed by commented-out
+/// context.
+fn build_commit_template(
+    repo: &Repository,
+    metadata: &StackMetadata,
+    current_branch: &str,
+    initial_message: &str,
+) -> Result<String> {
+    let mut template = initial_message.to_string();
+    if !template.ends_with('\n') {
+        template.push('\n');
+    }
+
+    template.push_str(
+        "\n# Please enter the commit message for your changes. Lines starting\n# with '#' will be ignored, and an empty message aborts the commit.\n#\n",
+    );
+
+    if metadata.branches.contains_key(current_branch) {
+        let stack = current_stack_sequence(metadata, current_branch);
+        let position = stack
+            .iter()
+            .position(|branch| branch == current_branch)
+            .map(|idx| idx + 1)
+            .unwrap_or(0);
+        template.push_str(&format!(
+            "# On branch {} ({} of {} in stack)\n",
+            current_branch,
+            position,
+            stack.len()
+        ));
+        let bottom = metadata.find_stack_bottom(current_branch);
+        if let Some(base) = metadata.get_parent(&bottom) {
+            template.push_str(&format!("#   {}\n", base));
+        }
+        for branch in &stack {
+            let marker = if branch == current_branch { "*" } else { " " };
+            template.push_str(&format!("# {} {}\n", marker, branch));
+        }
+    } else {
+        template.push_str(&format!("# On branch {} (not tracked by Pancake)\n", current_branch));
+    }
+
+    let staged = staged_changes(repo)?;
+    template.push_str("#\n");
+    if staged.is_empty() {
+        template.push_str("# No changes staged for commit\n");
+    } else {
+        template.push_str("# Changes to be committed:\n");
+        for (status, path) in staged {
+            template.push_str(&format!("#\t{:<12}{}\n", format!("{}:", status), path));
+        }
+    }
+
+    Ok(template)
+}
+
+fn read_commit_template(repo: &Repository) -> Result<Option<String>> {
+    let config = repo.config().context("failed to read git config")?;
+    let Ok(path) = config.get_path("commit.template") else {
+        return Ok(None);
+    };
+    let path = match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    };
+    // Relative template paths are resolved against the repository root, as git does
+    let path = match repo.workdir() {
+        Some(workdir) if path.is_relative() => workdir.join(path),
+        _ => path,
+    };
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read commit template {}", display_path(&path)))?;
+    Ok(Some(contents))
+}
+
+/// Lists the changes between HEAD and the index as `(status, path)` pairs.
+fn staged_changes(repo: &Repository) -> Result<Vec<(&'static str, String)>> {
+    let head_tree = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .context("failed to get HEAD tree")?;
+    let diff = repo
+        .diff_tree_to_index(Some(&head_tree), None, None)
+        .context("failed to diff the index against HEAD")?;
+
+    Ok(diff
+        .deltas()
+        .map(|delta| {
+            let status = match delta.status() {
+                git2::Delta::Added => "new file",
+                git2::Delta::Deleted => "deleted",
+                git2::Delta::Renamed => "renamed",
+                git2::Delta::Copied => "copied",
+                git2::Delta::Typechange => "typechange",
+                _ => "modified",
+            };
+            let path = delta
+                .new_file()
+                .path()
+                .or_else(|| delta.old_file().path())
+                .map(|path| path.display().to_string())
+                .unwrap_or_default();
+            (status, path)
+        })
+        .collect())
+}
+
+/// Commits the changes `tree` makes to HEAD onto `target`, a branch below
+/// `current_branch`, and returns the restack of the branches above it.
+fn commit_to_branch(
+    repo: &Repository,
+    metadata: &StackMetadata,
+    target: &str,
+    current_branch: &str,
+    tree: git2::Oid,
+    message: &str,
+    signer: Option<&CommitSigner>,
+) -> Result<PendingOperation> {
+    let head_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("failed to get HEAD commit")?;
+    let target_commit = resolve_branch_commit(repo, target)?;
+    let merged = merge_tree_ids(repo, head_commit.tree_id(), target_commit.tree_id(), tree)?
+        .ok_or_else(|| {
+            anyhow!(
+                "The staged changes do not apply cleanly to '{}'; nothing was committed.",
+                target
+            )
+        })?;
+    if merged == target_commit.tree_id() {
+        bail!(
+            "The staged changes are already part of '{}'; nothing was committed.",
+            target
+        );
+    }
+
+    // Branches above the target still sit on its old tip; remember it first
+    let descendants: Vec<String> = collect_branch_sequence(metadata, target)
+        .into_iter()
+        .skip(1)
+        .collect();
+    let mut state = PendingOperation::new(
+        OperationKind::Commit,
+        descendants,
+        current_branch.to_string(),
+    );
+    state.record_onto_bases(repo, metadata)?;
+
+    let signature = repo.signature().context(
+        "failed to get git signature. Ensure git user.name and user.email are configured.",
+    )?;
+    let merged = repo.find_tree(merged).context("failed to find tree")?;
+    let oid = write_commit(
+        repo,
+        signer,
+        &signature,
+        &signature,
+        message,
+        &merged,
+        &[&target_commit],
+    )?;
+    repo.reference(
+        &format!("refs/heads/{}", target),
+        oid,
+        true,
+        &format!("commit: {}", message.lines().next().unwrap_or("")),
+    )
+    .with_context(|| format!("failed to update branch '{}'", target))?;
+    Ok(state)
+}
+
+/// Explains why there is nothing to commit, listing the changes that are not
+/// staged yet.
+fn nothing_to_commit(repo: &Repository, branch: &str, command: &str) -> Result<anyhow::Error> {
+    let mut options = git2::StatusOptions::new();
+    options
+        .include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .context("failed to read working tree status")?;
+
+    let mut unstaged = Vec::new();
+    for entry in statuses.iter() {
+        let Some(path) = entry.path() else {
+            continue;
+        };
+        // Pancake's own state is not something to commit
+        if path.starts_with(".pancake/") {
+            continue;
+        }
+        let status = entry.status();
+        let label = if status.is_wt_new() {
+            "untracked"
+        } else if status.is_wt_deleted() {
+            "deleted"
+        } else if status.is_wt_typechange() {
+            "typechange"
+        } else if status.is_wt_modified() || status.is_wt_renamed() {
+            "modified"
+        } else {
+            continue;
+        };
+        unstaged.push(format!("  {:<11} {}", format!("{}:", label), path));
+    }
+
+    if unstaged.is_empty() {
+        return Ok(anyhow!(
+            "Nothing to commit on branch '{}': the working tree is clean. Use `--allow-empty` to create an empty commit anyway.",
+            branch
+        ));
+    }
+    Ok(anyhow!(
+        "Nothing to commit on branch '{}': no changes are staged.\n\nChanges not staged for commit:\n{}\n\nUse `{} -a` to stage all changes, `git add` to choose some, or `--allow-empty` to commit anyway.",
+        branch,
+        unstaged.join("\n"),
+        command
+    ))
+}
+
+/// Warns when amending HEAD would keep both its tree and its message.
+fn warn_on_noop_amend(repo: &Repository, message: &str) -> Result<()> {
+    let head_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("failed to get HEAD commit")?;
+    let tree = repo
+        .index()
+        .and_then(|mut index| index.write_tree())
+        .context("failed to write tree")?;
+    if tree == head_commit.tree_id()
+        && message.trim_end() == head_commit.message().unwrap_or("").trim_end()
+    {
+        eprintln!("Warning: the amend changes neither the tree nor the message of HEAD");
+    }
+    Ok(())
+}
+
+/// The initial contents of the commit message file and where they came from,
+/// as reported to the `prepare-commit-msg` hook.
+struct CommitMessageSeed {
+    contents: String,
+    source: &'static str,
+    commit: Option<git2::Oid>,
+    edit: bool,
+}
+
+impl CommitMessageSeed {
+    fn message(message: String) -> Self {
+        Self {
+            contents: message,
+            source: "message",
+            commit: None,
+            edit: false,
+        }
+    }
+}
+
+/// Produces the final commit message like `git commit`: run `prepare-commit-msg`
+/// on the seeded message file, open the editor if needed, add `trailers`, then
+/// run `commit-msg`.
+fn compose_commit_message(
+    repo: &Repository,
+    hooks: &CommitHooks,
+    seed: CommitMessageSeed,
+    trailers: &[(String, String)],
+) -> Result<String> {
+    let path = repo.path().join("PK_COMMIT_EDITMSG");
+    fs::write(&path, &seed.contents)
+        .with_context(|| format!("failed to write {}", display_path(&path)))?;
+
+    let path_arg = path.display().to_string();
+    let commit_arg = seed.commit.map(|oid| oid.to_string());
+    let mut prepare_args = vec![path_arg.as_str()];
+    if !seed.source.is_empty() {
+        prepare_args.push(seed.source);
+    }
+    if let Some(commit) = commit_arg.as_deref() {
+        prepare_args.push(commit);
+    }
+    hooks.run("prepare-commit-msg", &prepare_args)?;
+
+    if seed.edit {
+        launch_editor(repo, &path)?;
+    }
+
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", display_path(&path)))?;
+    let message = if seed.edit {
+        clean_commit_message(&contents)
+    } else {
+        contents
+    };
+    if message.trim().is_empty() {
+        bail!("Aborting commit due to empty commit message.");
+    }
+    let message = append_trailers(&message, trailers);
+
+    if !hooks.verify || hooks.find("commit-msg").is_none() {
+        return Ok(message);
+    }
+    fs::write(&path, &message)
+        .with_context(|| format!("failed to write {}", display_path(&path)))?;
+    hooks.run("commit-msg", &[path_arg.as_str()])?;
+    let message = fs::read_to_string(&path)
+        .with_context(|| format!("failed to read {}", display_path(&path)))?;
+    if message.trim().is_empty() {
+        bail!("Aborting commit due to empty commit message.");
+    }
+    Ok(message)
+}
+
+const CHANGE_ID_TRAILER: &str = "Change-Id";
+
+/// Collects the `--trailer`, `--signoff` and `--co-author` trailers of `pk commit`.
+fn commit_trailers(repo: &Repository, args: &CommitArgs) -> Result<Vec<(String, String)>> {
+    let mut trailers = args
+        .trailers
+        .iter()
+        .map(|trailer| parse_trailer(trailer))
+        .collect::<Result<Vec<_>>>()?;
+    for co_author in &args.co_authors {
+        let co_author = co_author.trim();
+        if !co_author.ends_with('>') || co_author.starts_with('<') || !co_author.contains(" <") {
+            bail!("Invalid co-author '{}'; expected 'Name <email>'", co_author);
+        }
+        trailers.push(("Co-authored-by".to_string(), co_author.to_string()));
+    }
+    if args.signoff {
+        let signature = repo.signature().context(
+            "failed to get git signature. Ensure git user.name and user.email are configured.",
+        )?;
+        trailers.push((
+            "Signed-off-by".to_string(),
+            format!(
+                "{} <{}>",
+                signature.name().unwrap_or(""),
+                signature.email().unwrap_or("")
+            ),
+        ));
+    }
+    Ok(trailers)
+}
+
+/// Parses `key=value` or `key: value` into a trailer.
+fn parse_trailer(trailer: &str) -> Result<(String, String)> {
+    let parsed = trailer
+        .find(['=', ':'])
+        .map(|idx| (trailer[..idx].trim(), trailer[idx + 1..].trim()))
+        .filter(|(key, value)| is_trailer_key(key) && !value.is_empty());
+    match parsed {
+        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+        None => bail!("Invalid trailer '{}'; expected 'key=value'", trailer),
+    }
+}
+
+fn is_trailer_key(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Returns the trailers in the last paragraph of `message`, or nothing when
+/// that paragraph is the subject or contains anything but trailers.
+fn message_trailers(message: &str) -> Vec<(&str, &str)> {
+    let Some((_, last)) = message.trim_end().rsplit_once("\n\n") else {
+        return Vec::new();
+    };
+    let trailers: Option<Vec<_>> = last
+        .trim_start_matches('\n')
+        .lines()
+        .map(|line| {
+            line.split_once(": ")
+                .filter(|(key, _)| is_trailer_key(key))
+                .map(|(key, value)| (key, value.trim()))
+        })
+        .collect();
+    trailers.unwrap_or_default()
+}
+
+/// Adds `trailers` to the trailer block of `message`, skipping ones it already
+/// has. A message keeps its first `Change-Id`.
+fn append_trailers(message: &str, trailers: &[(String, String)]) -> String {
+    let existing = message_trailers(message);
+    let mut present: Vec<(&str, &str)> = existing.clone();
+    let mut added = Vec::new();
+    for (key, value) in trailers {
+        let duplicate = present.iter().any(|(k, v)| {
+            k.eq_ignore_ascii_case(key) && (v == value || key == CHANGE_ID_TRAILER)
+        });
+        if !duplicate {
+            present.push((key, value));
+            added.push((key, value));
+        }
+    }
+    if added.is_empty() {
+        return message.to_string();
+    }
+
+    let mut message = message.trim_end().to_string();
+    if existing.is_empty() {
+        message.push('\n');
+    }
+    for (key, value) in added {
+        message.push_str(&format!("\n{}: {}", key, value));
+    }
+    message.push('\n');
+    message
+}
+
+/// Returns the `Change-Id` trailer of a commit message.
+fn change_id(message: &str) -> Option<&str> {
+    message_trailers(message)
+        .into_iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(CHANGE_ID_TRAILER))
+        .map(|(_, value)| value)
+}
+
+/// The `Change-Id` trailer for a new commit, or for one replacing `amended`,
+/// which keeps its ID. New IDs are only minted when `commit.change_id` is set.
+fn change_id_trailer(
+    repo: &Repository,
+    config: &PancakeConfig,
+    amended: Option<&git2::Commit>,
+) -> Result<Option<(String, String)>> {
+    if let Some(id) = amended.and_then(|commit| change_id(commit.message().unwrap_or(""))) {
+        return Ok(Some((CHANGE_ID_TRAILER.to_string(), id.to_string())));
+    }
+    if !config.commit.change_id {
+        return Ok(None);
+    }
+
+    let head = repo
+        .head()
+        .ok()
+        .and_then(|head| head.target())
+        .map(|oid| oid.to_string())
+        .unwrap_or_default();
+    let committer = repo
+        .signature()
+        .map(|signature| signature.to_string())
+        .unwrap_or_default();
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default();
+    let seed = format!(
+        "{} {} {} {}",
+        head,
+        committer,
+        now.as_nanos(),
+        std::process::id()
+    );
+    let id = git2::Oid::hash_object(git2::ObjectType::Blob, seed.as_bytes())
+        .context("failed to generate a Change-Id")?;
+    Ok(Some((CHANGE_ID_TRAILER.to_string(), format!("I{}", id))))
+}
+
+fn launch_editor(repo: &Repository, path: &Path) -> Result<()> {
+    let editor = resolve_editor(repo)?;
+    // Run through the shell like git does, so editors with arguments work
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .with_context(|| format!("failed to launch editor '{}'", editor))?;
+    if !status.success() {
+        bail!("Editor '{}' exited with an error; aborting commit", editor);
+    }
+    Ok(())
+}
+
+/// Returns the git directory shared by all worktrees, which holds the hooks.
+fn common_git_dir(repo: &Repository) -> PathBuf {
+    let git_dir = repo.path().to_path_buf();
+    if !repo.is_worktree() {
+        return git_dir;
+    }
+    match fs::read_to_string(git_dir.join("commondir")) {
+        Ok(contents) => git_dir.join(contents.trim()),
+        Err(_) => git_dir,
+    }
+}
+
+/// The repository's commit hooks, found in `core.hooksPath` or `.git/hooks`.
+struct CommitHooks {
+    dir: PathBuf,
+    workdir: PathBuf,
+    index_path: PathBuf,
+    /// Whether `pre-commit` and `commit-msg` run (`--no-verify` turns them off)
+    verify: bool,
+}
+
+impl CommitHooks {
+    fn new(repo: &Repository, verify: bool) -> Result<Self> {
+        let workdir = repo
+            .workdir()
+            .context("bare repositories are not supported by Pancake")?
+            .to_path_buf();
+        let config = repo.config().context("failed to read git config")?;
+        let dir = match config.get_path("core.hooksPath") {
+            Ok(path) if path.is_relative() => workdir.join(path),
+            Ok(path) => path,
+            Err(_) => common_git_dir(repo).join("hooks"),
+        };
+        Ok(Self {
+            dir,
+            workdir,
+            index_path: repo.path().join("index"),
+            verify,
+        })
+    }
+
+    fn find(&self, name: &str) -> Option<PathBuf> {
+        let path = self.dir.join(name);
+        let metadata = fs::metadata(&path).ok()?;
+        if !metadata.is_file() {
+            return None;
+        }
+        // Like git, ignore hooks that are not executable
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            if metadata.permissions().mode() & 0o111 == 0 {
+                return None;
+            }
+        }
+        Some(path)
+    }
+
+    /// Runs a hook if it exists, failing when it exits unsuccessfully.
+    fn run(&self, name: &str, args: &[&str]) -> Result<()> {
+        let Some(path) = self.find(name) else {
+            return Ok(());
+        };
+        let status = Command::new(&path)
+            .args(args)
+            .current_dir(&self.workdir)
+            .env("GIT_INDEX_FILE", &self.index_path)
+            .status()
+            .with_context(|| format!("failed to run the {} hook", name))?;
+        if !status.success() {
+            let skip_hint = if matches!(name, "pre-commit" | "commit-msg") {
+                " Use `--no-verify` to skip it."
+            } else {
+                ""
+            };
+            bail!("The {} hook failed; commit aborted.{}", name, skip_hint);
+        }
+        Ok(())
+    }
+
+    fn pre_commit(&self, repo: &Repository) -> Result<()> {
+        if !self.verify || self.find("pre-commit").is_none() {
+            return Ok(());
+        }
+        self.run("pre-commit", &[])?;
+        // The hook may have staged changes of its own
+        repo.index()
+            .and_then(|mut index| index.read(false))
+            .context("failed to reload repository index")
+    }
+
+    /// Runs `post-commit`, whose outcome cannot affect the commit.
+    fn post_commit(&self) {
+        if let Err(err) = self.run("post-commit", &[]) {
+            eprintln!("Warning: {}", err);
+        }
+    }
+}
+
+/// Strips comment lines and surrounding blank lines, like `git commit --cleanup=strip`.
+fn clean_commit_message(contents: &str) -> String {
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.trim_end())
+        .collect();
+    let mut message = String::new();
+    let mut pending_blank = false;
+    for line in lines {
+        if line.is_empty() {
+            pending_blank = !message.is_empty();
+            continue;
+        }
+        if pending_blank {
+            message.push('\n');
+            pending_blank = false;
+        }
+        message.push_str(line);
+        message.push('\n');
+    }
+    message
+}
+
+/// Picks the editor from `defaults.editor`, `core.editor`, `$GIT_EDITOR`,
+/// `$VISUAL` and `$EDITOR`, in that order.
+fn resolve_editor(repo: &Repository) -> Result<String> {
+    let global = GlobalConfig::load()?;
+    if let Some(editor) = global.defaults.editor.filter(|editor| !editor.is_empty()) {
+        return Ok(editor);
+    }
+    if let Ok(editor) = repo
+        .config()
+        .context("failed to read git config")?
+        .get_string("core.editor")
+        && !editor.is_empty()
+    {
+        return Ok(editor);
+    }
+    for variable in ["GIT_EDITOR", "VISUAL", "EDITOR"] {
+        if let Ok(editor) = std::env::var(variable)
+            && !editor.is_empty()
+        {
+            return Ok(editor);
+        }
+    }
+    Ok("vi".to_string())
+}
+
+/// Replaces HEAD with a commit of the current index and `message`.
+fn amend_head(
+    repo: &Repository,
+    message: &str,
+    signer: Option<&CommitSigner>,
+) -> Result<git2::Oid> {
+    // Get the signature for the commit
+    let signature = repo.signature()
+        .context("failed to get git signature. Ensure git user.name and user.email are configured.")?;
+
+    // Amend the last commit
+    let head_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("failed to get HEAD commit")?;
+
+    // Get the current index tree
+    let mut index = repo.index().context("failed to get repository index")?;
+    let tree_oid = index.write_tree().context("failed to write tree")?;
+    let tree = repo.find_tree(tree_oid).context("failed to find tree")?;
+
+    // Amend the commit, keeping its parents
+    let parents: Vec<git2::Commit> = head_commit.parents().collect();
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+    let oid = write_commit(repo, signer, &signature, &signature, message, &tree, &parents)?;
+    update_head(repo, oid, &format!("commit (amend): {}", message.lines().next().unwrap_or("")))?;
+    Ok(oid)
+}
+
+fn stage_all(repo: &Repository) -> Result<()> {
+    let mut index = repo.index().context("failed to get repository index")?;
+    index.add_all(["."].iter(), git2::IndexAddOption::DEFAULT, None)
+        .context("failed to stage changes")?;
+    index.write().context("failed to write index")?;
+    Ok(())
+}
+
+fn commit_tree(
+    repo: &Repository,
+    message: &str,
+    tree_oid: git2::Oid,
+    signer: Option<&CommitSigner>,
+) -> Result<git2::Oid> {
+    let signature = repo.signature()
+        .context("failed to get git signature. Ensure git user.name and user.email are configured.")?;
+
+    let tree = repo.find_tree(tree_oid).context("failed to find tree")?;
+
+    // Get the parent commit (HEAD)
+    let parent_commit = repo
+        .head()
+        .and_then(|head| head.peel_to_commit())
+        .context("failed to get parent commit")?;
+
+    // Create the commit
+    let oid = write_commit(
+        repo,
+        signer,
+        &signature,
+        &signature,
+        message,
+        &tree,
+        &[&parent_commit],
+    )?;
+    update_head(repo, oid, &format!("commit: {}", message.lines().next().unwrap_or("")))?;
+    Ok(oid)
+}
+
+/// Writes a commit object without moving any reference, signing it when a
+/// signer is configured.
+fn write_commit(
+    repo: &Repository,
+    signer: Option<&CommitSigner>,
+    author: &git2::Signature,
+    committer: &git2::Signature,
+    message: &str,
+    tree: &git2::Tree,
+    parents: &[&git2::Commit],
+) -> Result<git2::Oid> {
+    let Some(signer) = signer else {
+        return repo
+            .commit(None, author, committer, message, tree, parents)
+            .context("failed to create commit");
+    };
+
+    let buffer = repo
+        .commit_create_buffer(author, committer, message, tree, parents)
+        .context("failed to create commit")?;
+    let content = std::str::from_utf8(&buffer).context("commit contents are not valid UTF-8")?;
+    let signature = signer.sign(content)?;
+    repo.commit_signed(content, &signature, None)
+        .context("failed to create signed commit")
+}
+
+/// Points the branch HEAD is on (or HEAD itself when detached) at `oid`.
+fn update_head(repo: &Repository, oid: git2::Oid, reflog_message: &str) -> Result<()> {
+    let mut head = repo.head().context("unable to resolve current HEAD")?;
+    head.set_target(oid, reflog_message)
+        .context("failed to update HEAD")?;
+    Ok(())
+}
+
+/// Maps `-S`/`--no-gpg-sign` to an override of `commit.gpgsign`.
+fn signing_override(gpg_sign: Option<&str>, no_gpg_sign: bool) -> Option<bool> {
+    if no_gpg_sign {
+        Some(false)
+    } else {
+        gpg_sign.map(|_| true)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SigningFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+/// Signs commit objects the way git does, following `gpg.format`,
+/// `user.signingkey` and the `gpg.*.program` settings.
+struct CommitSigner {
+    format: SigningFormat,
+    program: String,
+    key: String,
+    /// Where `ssh-keygen` reads the commit from and writes the signature to.
+    git_dir: PathBuf,
+}
+
+impl CommitSigner {
+    /// Returns the signer to use, or `None` when commits should not be signed.
+    /// `sign` overrides `commit.gpgsign` and `key` overrides `user.signingkey`.
+    fn resolve(repo: &Repository, sign: Option<bool>, key: Option<&str>) -> Result<Option<Self>> {
+        let config = repo.config().context("failed to read git config")?;
+        let enabled = match sign {
+            Some(sign) => sign,
+            None => config.get_bool("commit.gpgsign").unwrap_or(false),
+        };
+        if !enabled {
+            return Ok(None);
+        }
+
+        let format_name = config
+            .get_string("gpg.format")
+            .unwrap_or_else(|_| "openpgp".to_string());
+        let (format, default_program) = match format_name.as_str() {
+            "openpgp" => (SigningFormat::OpenPgp, "gpg"),
+            "x509" => (SigningFormat::X509, "gpgsm"),
+            "ssh" => (SigningFormat::Ssh, "ssh-keygen"),
+            other => bail!("unsupported gpg.format '{}'", other),
+        };
+        let mut program = config
+            .get_string(&format!("gpg.{}.program", format_name))
+            .ok();
+        if format == SigningFormat::OpenPgp && program.is_none() {
+            program = config.get_string("gpg.program").ok();
+        }
+        let program = program.unwrap_or_else(|| default_program.to_string());
+
+        let key = match key.filter(|key| !key.is_empty()) {
+            Some(key) => key.to_string(),
+            None => match config.get_string("user.signingkey") {
+                Ok(key) => key,
+                Err(_) if format == SigningFormat::Ssh => {
+                    bail!("SSH signing requires user.signingkey to be set")
+                }
+                // gpg picks the key matching the committer identity
+                Err(_) => {
+                    let committer = repo.signature().context(
+                        "failed to get git signature. Ensure git user.name and user.email are configured.",
+                    )?;
+                    format!(
+                        "{} <{}>",
+                        committer.name().unwrap_or(""),
+                        committer.email().unwrap_or("")
+                    )
+                }
+            },
+        };
+
+        Ok(Some(Self {
+            format,
+            program,
+            key,
+            git_dir: repo.path().to_path_buf(),
+        }))
+    }
+
+    /// Returns the detached signature for `content`.
+    fn sign(&self, content: &str) -> Result<String> {
+        match self.format {
+            SigningFormat::Ssh => self.sign_ssh(content),
+            SigningFormat::OpenPgp | SigningFormat::X509 => self.sign_gpg(content),
+        }
+    }
+
+    fn sign_gpg(&self, content: &str) -> Result<String> {
+        use std::io::Write;
+
+        let mut child = Command::new(&self.program)
+            .args(["--status-fd=2", "-bsau", &self.key])
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()
+            .with_context(|| format!("failed to run '{}' to sign the commit", self.program))?;
+        child
+            .stdin
+            .take()
+            .context("failed to open signing program input")?
+            .write_all(content.as_bytes())
+            .context("failed to pass the commit to the signing program")?;
+        let output = child
+            .wait_with_output()
+            .context("failed to wait for the signing program")?;
+
+        let status = String::from_utf8_lossy(&output.stderr);
+        if !output.status.success() || !status.contains("[GNUPG:] SIG_CREATED ") {
+            bail!(
+                "failed to sign the commit with '{}'\n\n{}",
+                self.program,
+                status.trim()
+            );
+        }
+        String::from_utf8(output.stdout).context("signature is not valid UTF-8")
+    }
+
+    fn sign_ssh(&self, content: &str) -> Result<String> {
+        // `create_dir` refuses an existing path, so nobody else can have
+        // prepared the files ssh-keygen reads and writes
+        let scratch = self.git_dir.join(format!("pk-sign-{}", std::process::id()));
+        fs::create_dir(&scratch)
+            .with_context(|| format!("failed to create {}", display_path(&scratch)))?;
+        let result = self.sign_ssh_in(&scratch, content);
+        let _ = fs::remove_dir_all(&scratch);
+        result
+    }
+
+    fn sign_ssh_in(&self, scratch: &Path, content: &str) -> Result<String> {
+        let buffer = scratch.join("commit");
+        fs::write(&buffer, content)
+            .with_context(|| format!("failed to write {}", display_path(&buffer)))?;
+
+        // A literal public key signs through ssh-agent; anything else is a key file
+        let literal = self
+            .key
+            .strip_prefix("key::")
+            .or_else(|| self.key.starts_with("ssh-").then_some(self.key.as_str()));
+        let mut command = Command::new(&self.program);
+        command.args(["-Y", "sign", "-n", "git", "-f"]);
+        match literal {
+            Some(public_key) => {
+                let key_file = scratch.join("key.pub");
+                fs::write(&key_file, public_key)
+                    .with_context(|| format!("failed to write {}", display_path(&key_file)))?;
+                command.arg(key_file).arg("-U");
+            }
+            None => {
+                let key_file = match self.key.strip_prefix("~/") {
+                    Some(rest) => std::env::var_os("HOME")
+                        .map(|home| PathBuf::from(home).join(rest))
+                        .unwrap_or_else(|| PathBuf::from(&self.key)),
+                    None => PathBuf::from(&self.key),
+                };
+                command.arg(key_file);
+            }
+        }
+        let output = command
+            .arg(&buffer)
+            .output()
+            .with_context(|| format!("failed to run '{}' to sign the commit", self.program))?;
+        if !output.status.success() {
+            bail!(
+                "failed to sign the commit with '{}'\n\n{}",
+                self.program,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+
+        let signature = scratch.join("commit.sig");
+        fs::read_to_string(&signature)
+            .with_context(|| format!("failed to read {}", display_path(&signature)))
+    }
+}
+
+/// Runs `git add -p` on `paths` (everything when empty) so the user can pick hunks.
+fn stage_patch(repo_root: &Path, paths: &[PathBuf]) -> Result<()> {
+    let status = Command::new("git")
+        .args(["add", "--patch", "--"])
+        .args(paths)
+        .current_dir(repo_root)
+        .status()
+        .context("failed to run git add --patch")?;
+    if !status.success() {
+        bail!("`git add --patch` failed; aborting commit");
+    }
+    Ok(())
+}
+
+/// Stages the working tree contents of the tracked files under `paths`.
+fn stage_paths(repo: &Repository, paths: &[PathBuf]) -> Result<()> {
+    let head_tree = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .context("failed to get HEAD tree")?;
+    let mut index = repo.index().context("failed to get repository index")?;
+
+    // Like `git commit <paths>`, untracked files are never picked up
+    for path in paths {
+        let tracked = path.as_os_str().is_empty()
+            || head_tree.get_path(path).is_ok()
+            || index.iter().any(|entry| {
+                Path::new(std::str::from_utf8(&entry.path).unwrap_or("")).starts_with(path)
+            });
+        if !tracked {
+            bail!(
+                "pathspec '{}' did not match any files known to git",
+                path.display()
+            );
+        }
+    }
+
+    let pathspecs: Vec<&Path> = paths.iter().map(PathBuf::as_path).collect();
+    index
+        .update_all(pathspecs.iter(), None)
+        .context("failed to stage changes")?;
+    index.write().context("failed to write index")
+}
+
+/// The HEAD tree with `paths` taken from the index, so that a path-limited
+/// commit leaves other staged changes for later.
+fn path_limited_tree(repo: &Repository, paths: &[PathBuf]) -> Result<git2::Oid> {
+    let head_tree = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .context("failed to get HEAD tree")?;
+    let index = repo.index().context("failed to get repository index")?;
+    let covers = |candidate: &Path| {
+        paths
+            .iter()
+            .any(|path| path.as_os_str().is_empty() || candidate.starts_with(path))
+    };
+
+    let mut only = git2::Index::new().context("failed to create in-memory index")?;
+    only.read_tree(&head_tree).context("failed to read HEAD tree")?;
+    let stale: Vec<PathBuf> = only
+        .iter()
+        .map(|entry| PathBuf::from(String::from_utf8_lossy(&entry.path).to_string()))
+        .filter(|path| covers(path))
+        .collect();
+    for path in stale {
+        only.remove_path(&path).context("failed to build commit tree")?;
+    }
+    for entry in index.iter() {
+        let path = PathBuf::from(String::from_utf8_lossy(&entry.path).to_string());
+        if covers(&path) {
+            only.add(&entry).context("failed to build commit tree")?;
+        }
+    }
+    only.write_tree_to(repo).context("failed to write tree")
+}
+
+/// Resolves a path given on the command line, relative to the current
+/// directory, to a path relative to the repository root.
+fn repo_relative_path(workdir: &Path, path: &Path) -> Result<PathBuf> {
+    let cwd = std::env::current_dir().context("failed to read current directory")?;
+    let cwd = cwd.canonicalize().unwrap_or(cwd);
+    let workdir = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf());
+
+    let mut resolved = PathBuf::new();
+    for component in cwd.join(path).components() {
+        match component {
+            std::path::Component::CurDir => {}
+            std::path::Component::ParentDir => {
+                resolved.pop();
+            }
+            other => resolved.push(other),
+        }
+    }
+    resolved
+        .strip_prefix(&workdir)
+        .map(Path::to_path_buf)
+        .map_err(|_| anyhow!("'{}' is outside the repository", path.display()))
+}
+
+fn handle_amend(args: AmendArgs) -> Result<()> {
+    let repo = Repository::discover(".").context("`pk amend` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    if args.continue_rebase && args.abort {
+        bail!("Cannot use --continue and --abort together.");
+    }
+
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    if args.continue_rebase {
+        return continue_operation(&repo, &repo_root, &metadata, OperationKind::Amend);
+    }
+
+    if args.abort {
+        return abort_operation(&repo_root, OperationKind::Amend);
+    }
+
+    ensure_no_active_operation(&repo_root)?;
+
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if !head.is_branch() {
+        bail!("HEAD is not currently on a branch");
+    }
+    let current_branch = head
+        .shorthand()
+        .ok_or_else(|| anyhow!("unable to get current branch name"))?
+        .to_string();
+
+    if metadata.is_frozen(&current_branch) && !args.force {
+        bail!(
+            "Branch '{}' is frozen. Use `--force` to amend it anyway, or `pk branch unfreeze`.",
+            current_branch
+        );
+    }
+
+    if args.all {
+        stage_all(&repo)?;
+    }
+
+    let hooks = CommitHooks::new(&repo, !args.no_verify)?;
+    hooks.pre_commit(&repo)?;
+
+    let head_commit = head.peel_to_commit().context("failed to get HEAD commit")?;
+    let seed = match args.message {
+        Some(message) => CommitMessageSeed::message(message),
+        None if args.edit => CommitMessageSeed {
+            contents: build_commit_template(
+                &repo,
+                &metadata,
+                &current_branch,
+                head_commit.message().unwrap_or(""),
+            )?,
+            source: "commit",
+            commit: Some(head_commit.id()),
+            edit: true,
+        },
+        None => CommitMessageSeed {
+            contents: head_commit.message().unwrap_or("").to_string(),
+            source: "commit",
+            commit: Some(head_commit.id()),
+            edit: false,
+        },
+    };
+    let config = PancakeConfig::load(&repo_root)?;
+    let trailers: Vec<_> = change_id_trailer(&repo, &config, Some(&head_commit))?
+        .into_iter()
+        .collect();
+    let message = compose_commit_message(&repo, &hooks, seed, &trailers)?;
+
+    // Descendants still sit on the old commit; remember it before rewriting
+    let descendants: Vec<String> = collect_branch_sequence(&metadata, &current_branch)
+        .into_iter()
+        .skip(1)
+        .collect();
+    let mut state = PendingOperation::new(OperationKind::Amend, descendants, current_branch.clone());
+    state.record_onto_bases(&repo, &metadata)?;
+
+    let signer = CommitSigner::resolve(
+        &repo,
+        signing_override(args.gpg_sign.as_deref(), args.no_gpg_sign),
+        args.gpg_sign.as_deref(),
+    )?;
+    warn_on_noop_amend(&repo, &message)?;
+    amend_head(&repo, &message, signer.as_ref())?;
+    hooks.post_commit();
+    record_change_ids(&repo, &repo_root, &metadata)?;
+    println!("Amended commit on branch '{}'", current_branch);
+
+    if state.branches.is_empty() {
+        return Ok(());
+    }
+    execute_operation(&repo, &repo_root, &metadata, state)
+}
+
+fn handle_absorb(args: AbsorbArgs) -> Result<()> {
+    let repo =
+        Repository::discover(".").context("`pk absorb` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    if args.continue_rebase && args.abort {
+        bail!("Cannot use --continue and --abort together.");
+    }
+
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    if args.continue_rebase {
+        return continue_operation(&repo, &repo_root, &metadata, OperationKind::Absorb);
+    }
+
+    if args.abort {
+        return abort_operation(&repo_root, OperationKind::Absorb);
+    }
+
+    ensure_no_active_operation(&repo_root)?;
+
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if !head.is_branch() {
+        bail!("HEAD is not currently on a branch");
+    }
+    let current_branch = head
+        .shorthand()
+        .ok_or_else(|| anyhow!("unable to get current branch name"))?
+        .to_string();
+    if !metadata.branches.contains_key(&current_branch) {
+        bail!(
+            "Current branch '{}' is not tracked by Pancake",
+            current_branch
+        );
+    }
+
+    if args.all {
+        let mut index = repo.index().context("failed to get repository index")?;
+        index
+            .update_all(["."].iter(), None)
+            .context("failed to stage changes")?;
         index.write().context("failed to write index")?;
     }
 
-    // Get the signature for the commit
-    let signature = repo.signature()
-        .context("failed to get git signature. Ensure git user.name and user.email are configured.")?;
+    let stack = BranchChain::collect(&repo, &metadata, None, &current_branch)?;
+    let mut unplaced = Vec::new();
+    let hunks = staged_hunks(&repo, &mut unplaced)?;
+    if hunks.is_empty() && unplaced.is_empty() {
+        bail!(
+            "No staged changes to absorb. Stage the hunks to fold in with `git add`, or pass `--all`."
+        );
+    }
+
+    let targets = assign_hunks(&repo, &stack, &metadata, hunks, &mut unplaced)?;
+    for (location, reason) in &unplaced {
+        println!("Could not absorb {}: {}", location, reason);
+    }
+    if targets.is_empty() {
+        bail!("No staged hunks could be absorbed; they were left staged.");
+    }
+
+    // Every branch from the lowest target upward gets rewritten
+    let lowest = targets
+        .iter()
+        .map(|target| stack.branch_index(target.commit))
+        .min()
+        .unwrap_or(0);
+    if let Some((frozen, _)) = stack.branches[lowest..]
+        .iter()
+        .find(|(branch, _)| metadata.is_frozen(branch))
+    {
+        bail!(
+            "Cannot absorb into '{}' without rewriting frozen branch '{}'.",
+            stack.branches[lowest].0,
+            frozen
+        );
+    }
+
+    let signer = CommitSigner::resolve(&repo, None, None)?;
+    let new_tips = stack.rewrite(&repo, &targets, args.fixup, signer.as_ref())?;
+
+    let rewritten: Vec<&String> = stack.branches[lowest..]
+        .iter()
+        .map(|(branch, _)| branch)
+        .collect();
+    let restack = branches_stacked_on(&metadata, &rewritten);
+    let mut state = PendingOperation::new(OperationKind::Absorb, restack, current_branch.clone());
+    state.record_onto_bases(&repo, &metadata)?;
+
+    for (branch, tip) in &new_tips {
+        repo.reference(&format!("refs/heads/{}", branch), *tip, true, "pk absorb")
+            .with_context(|| format!("failed to update branch '{}'", branch))?;
+    }
+
+    for target in &targets {
+        let commit = repo
+            .find_commit(target.commit)
+            .context("failed to find commit")?;
+        println!(
+            "Absorbed {} into {} on '{}'",
+            target.hunk.location(),
+            describe_commit(&commit),
+            stack.branches[stack.branch_index(target.commit)].0
+        );
+    }
+    if !unplaced.is_empty() {
+        println!("Left {} hunk(s) staged", unplaced.len());
+    }
+    record_change_ids(&repo, &repo_root, &metadata)?;
+
+    if state.branches.is_empty() {
+        return Ok(());
+    }
+    if has_uncommitted_changes(&repo)? {
+        run_git_checked(&repo_root, &["stash", "push", "-m", "pk absorb"])?;
+        state.autostash = true;
+    }
+    execute_operation(&repo, &repo_root, &metadata, state)
+}
+
+/// A staged change to a contiguous run of lines in HEAD.
+struct StagedHunk {
+    path: PathBuf,
+    /// First replaced line of the HEAD version, 1-based; for pure additions the
+    /// line the new lines follow
+    old_start: usize,
+    old_lines: Vec<Vec<u8>>,
+    new_lines: Vec<Vec<u8>>,
+}
+
+impl StagedHunk {
+    fn location(&self) -> String {
+        format!("{}:{}", self.path.display(), self.old_start)
+    }
+}
+
+/// A staged hunk placed in the stack commit that last touched its lines.
+struct AbsorbTarget {
+    commit: git2::Oid,
+    /// Path of the file in the target commit
+    path: PathBuf,
+    /// Index of the first replaced line in the target commit's version of the file
+    start: usize,
+    hunk: StagedHunk,
+}
+
+/// A run of stacked branches, each with the commits it adds on top of its
+/// parent (oldest first).
+struct BranchChain<'repo> {
+    base: git2::Oid,
+    branches: Vec<(String, Vec<git2::Commit<'repo>>)>,
+}
+
+impl<'repo> BranchChain<'repo> {
+    /// Collects the branches from `bottom` (or, when `None`, the bottom of the
+    /// stack) up to `top`.
+    fn collect(
+        repo: &'repo Repository,
+        metadata: &StackMetadata,
+        bottom: Option<&str>,
+        top: &str,
+    ) -> Result<Self> {
+        let mut chain = vec![top.to_string()];
+        while Some(chain.last().unwrap().as_str()) != bottom
+            && let Some(parent) = metadata.get_parent(chain.last().unwrap())
+            && metadata.branches.contains_key(&parent)
+        {
+            chain.push(parent);
+        }
+        chain.reverse();
+
+        let bottom_parent = metadata
+            .get_parent(&chain[0])
+            .ok_or_else(|| anyhow!("Branch '{}' has no recorded parent", chain[0]))?;
+        let base = resolve_branch_commit(repo, &bottom_parent)?.id();
+
+        let mut branches = Vec::new();
+        let mut parent_tip = base;
+        let mut parent_name = bottom_parent;
+        for name in chain {
+            let tip = resolve_branch_commit(repo, &name)?.id();
+            if tip != parent_tip
+                && !repo
+                    .graph_descendant_of(tip, parent_tip)
+                    .context("failed to compare branch history")?
+            {
+                bail!(
+                    "Branch '{}' is not based on the tip of '{}'. Run `pk restack` first.",
+                    name,
+                    parent_name
+                );
+            }
+
+            let mut commits = unmerged_commits(repo, tip, &[parent_tip])?;
+            commits.reverse();
+            if let Some(merge) = commits.iter().find(|commit| commit.parent_count() != 1) {
+                bail!(
+                    "Cannot rewrite branch '{}' because it contains merge commit {}",
+                    name,
+                    describe_commit(merge)
+                );
+            }
+
+            branches.push((name.clone(), commits));
+            parent_tip = tip;
+            parent_name = name;
+        }
+
+        Ok(Self { base, branches })
+    }
+
+    fn owner(&self, commit: git2::Oid) -> Option<&str> {
+        self.branches
+            .iter()
+            .find(|(_, commits)| commits.iter().any(|candidate| candidate.id() == commit))
+            .map(|(branch, _)| branch.as_str())
+    }
+
+    fn branch_index(&self, commit: git2::Oid) -> usize {
+        self.branches
+            .iter()
+            .position(|(_, commits)| commits.iter().any(|candidate| candidate.id() == commit))
+            .unwrap_or(0)
+    }
+
+    /// Replays the stack onto its base, folding each target into its commit or,
+    /// with `fixup`, into a `fixup!` commit at the tip of the owning branch.
+    /// Returns the new tip of every branch that changed.
+    fn rewrite(
+        &self,
+        repo: &Repository,
+        targets: &[AbsorbTarget],
+        fixup: bool,
+        signer: Option<&CommitSigner>,
+    ) -> Result<Vec<(String, git2::Oid)>> {
+        let committer = repo.signature().context(
+            "failed to get git signature. Ensure git user.name and user.email are configured.",
+        )?;
+        let mut rewritten: HashMap<git2::Oid, git2::Oid> = HashMap::new();
+        let mut new_tips = Vec::new();
+        let mut tip = self.base;
+
+        for (branch, commits) in &self.branches {
+            let old_tip = commits.last().map(|commit| commit.id()).unwrap_or(tip);
+
+            for commit in commits {
+                let parent = commit
+                    .parent_id(0)
+                    .context("failed to find parent commit")?;
+                let onto = rewritten.get(&parent).copied().unwrap_or(parent);
+                let own: Vec<&AbsorbTarget> = targets
+                    .iter()
+                    .filter(|target| !fixup && target.commit == commit.id())
+                    .collect();
+                let mut tree = commit.tree_id();
+                if !own.is_empty() {
+                    tree = absorb_into_tree(repo, commit, &own)?;
+                }
+
+                tip = replay_commit(repo, commit, onto, tree, &committer, signer)?.ok_or_else(|| {
+                    anyhow!(
+                        "Absorbing would conflict with {}; nothing was changed.",
+                        describe_commit(commit)
+                    )
+                })?;
+                if tip != commit.id() {
+                    rewritten.insert(commit.id(), tip);
+                }
+            }
+
+            if fixup {
+                for commit in commits {
+                    let own: Vec<&AbsorbTarget> = targets
+                        .iter()
+                        .filter(|target| target.commit == commit.id())
+                        .collect();
+                    if own.is_empty() {
+                        continue;
+                    }
+                    let fixed = absorb_into_tree(repo, commit, &own)?;
+                    let parent = repo
+                        .find_commit(tip)
+                        .context("failed to find parent commit")?;
+                    let tree = merge_tree_ids(repo, commit.tree_id(), parent.tree_id(), fixed)?
+                        .ok_or_else(|| {
+                            anyhow!(
+                                "Absorbing would conflict with '{}'; nothing was changed.",
+                                branch
+                            )
+                        })?;
+                    let tree = repo.find_tree(tree).context("failed to find tree")?;
+                    let message = format!("fixup! {}", commit.summary().unwrap_or(""));
+                    tip = write_commit(
+                        repo,
+                        signer,
+                        &committer,
+                        &committer,
+                        &message,
+                        &tree,
+                        &[&parent],
+                    )
+                    .context("failed to write fixup commit")?;
+                }
+                if let Some(last) = commits.last() {
+                    rewritten.insert(last.id(), tip);
+                }
+            }
+
+            if tip != old_tip {
+                new_tips.push((branch.clone(), tip));
+            }
+        }
+
+        Ok(new_tips)
+    }
+}
+
+/// Collects the staged changes as zero-context hunks. Changes that cannot be
+/// absorbed at all, such as new or binary files, are appended to `unplaced` as
+/// `(location, reason)`.
+fn staged_hunks(
+    repo: &Repository,
+    unplaced: &mut Vec<(String, String)>,
+) -> Result<Vec<StagedHunk>> {
+    let head_tree = repo
+        .head()
+        .and_then(|head| head.peel_to_tree())
+        .context("failed to get HEAD tree")?;
+    let index = repo.index().context("failed to get repository index")?;
+    let mut options = git2::DiffOptions::new();
+    options.context_lines(0);
+    let diff = repo
+        .diff_tree_to_index(Some(&head_tree), Some(&index), Some(&mut options))
+        .context("failed to diff staged changes")?;
+
+    let mut hunks = Vec::new();
+    for idx in 0..diff.deltas().len() {
+        let Some(delta) = diff.get_delta(idx) else {
+            continue;
+        };
+        let path = delta
+            .new_file()
+            .path()
+            .or_else(|| delta.old_file().path())
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        let location = path.display().to_string();
+        match delta.status() {
+            git2::Delta::Modified => {}
+            git2::Delta::Added => {
+                unplaced.push((
+                    location,
+                    "new files have no commit to absorb into".to_string(),
+                ));
+                continue;
+            }
+            git2::Delta::Deleted => {
+                unplaced.push((location, "deleted files cannot be absorbed".to_string()));
+                continue;
+            }
+            _ => {
+                unplaced.push((location, "only content changes can be absorbed".to_string()));
+                continue;
+            }
+        }
+
+        let patch = git2::Patch::from_diff(&diff, idx).context("failed to diff staged changes")?;
+        let Some(patch) = patch.filter(|patch| !patch.delta().flags().is_binary()) else {
+            unplaced.push((location, "binary files cannot be absorbed".to_string()));
+            continue;
+        };
+
+        for hunk_idx in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(hunk_idx).context("failed to read diff hunk")?;
+            let mut staged = StagedHunk {
+                path: path.clone(),
+                old_start: hunk.old_start() as usize,
+                old_lines: Vec::new(),
+                new_lines: Vec::new(),
+            };
+            for line_idx in 0..line_count {
+                let line = patch
+                    .line_in_hunk(hunk_idx, line_idx)
+                    .context("failed to read diff line")?;
+                match line.origin() {
+                    '-' => staged.old_lines.push(line.content().to_vec()),
+                    '+' => staged.new_lines.push(line.content().to_vec()),
+                    _ => {}
+                }
+            }
+            let ends_with_newline =
+                |lines: &[Vec<u8>]| lines.last().map(|line| line.ends_with(b"\n"));
+            if let (Some(old), Some(new)) = (
+                ends_with_newline(&staged.old_lines),
+                ends_with_newline(&staged.new_lines),
+            ) && old != new
+            {
+                unplaced.push((
+                    staged.location(),
+                    "changes the newline at the end of the file".to_string(),
+                ));
+                continue;
+            }
+            hunks.push(staged);
+        }
+    }
+
+    Ok(hunks)
+}
+
+/// Blames the lines each hunk replaces (or, for pure additions, the lines
+/// around it) and places the hunk in the stack commit that last touched all
+/// of them. Hunks that cannot be placed are appended to `unplaced`.
+fn assign_hunks(
+    repo: &Repository,
+    stack: &BranchChain,
+    metadata: &StackMetadata,
+    hunks: Vec<StagedHunk>,
+    unplaced: &mut Vec<(String, String)>,
+) -> Result<Vec<AbsorbTarget>> {
+    let mut blames: HashMap<PathBuf, git2::Blame> = HashMap::new();
+    let mut targets = Vec::new();
+
+    for hunk in hunks {
+        if !blames.contains_key(&hunk.path) {
+            let mut options = git2::BlameOptions::new();
+            options.oldest_commit(stack.base);
+            let blame = repo
+                .blame_file(&hunk.path, Some(&mut options))
+                .with_context(|| format!("failed to blame {}", hunk.path.display()))?;
+            blames.insert(hunk.path.clone(), blame);
+        }
+        let blame = &blames[&hunk.path];
+
+        let lines: Vec<usize> = if hunk.old_lines.is_empty() {
+            vec![hunk.old_start, hunk.old_start + 1]
+        } else {
+            (hunk.old_start..hunk.old_start + hunk.old_lines.len()).collect()
+        };
+        // (line in HEAD, commit, path in commit, line in commit) for every blamed line
+        let origins: Vec<(usize, git2::Oid, PathBuf, usize)> = lines
+            .iter()
+            .filter(|line| **line > 0)
+            .filter_map(|line| {
+                let blamed = blame.get_line(*line)?;
+                let path = blamed.path().unwrap_or(&hunk.path).to_path_buf();
+                let orig_line = blamed.orig_start_line() + line - blamed.final_start_line();
+                Some((*line, blamed.orig_commit_id(), path, orig_line))
+            })
+            .collect();
+
+        let Some((head_line, commit, path, orig_line)) = origins.first().cloned() else {
+            unplaced.push((hunk.location(), "no surrounding lines to blame".to_string()));
+            continue;
+        };
+        if origins
+            .iter()
+            .any(|(_, other, other_path, _)| *other != commit || *other_path != path)
+        {
+            unplaced.push((
+                hunk.location(),
+                "touches lines from more than one commit".to_string(),
+            ));
+            continue;
+        }
+        let Some(owner) = stack.owner(commit) else {
+            unplaced.push((
+                hunk.location(),
+                "last changed outside the stack".to_string(),
+            ));
+            continue;
+        };
+        if metadata.is_frozen(owner) {
+            unplaced.push((
+                hunk.location(),
+                format!("last changed on frozen branch '{}'", owner),
+            ));
+            continue;
+        }
+
+        // Pure additions go right after the line above them (or before the line below)
+        let start = if hunk.old_lines.is_empty() && head_line == hunk.old_start {
+            orig_line
+        } else {
+            orig_line - 1
+        };
+
+        let target_commit = repo.find_commit(commit).context("failed to find commit")?;
+        let contents = read_blob_lines(repo, &target_commit, &path)?;
+        let matches = contents
+            .get(start..start + hunk.old_lines.len())
+            .is_some_and(|existing| existing == hunk.old_lines.as_slice());
+        if !matches {
+            unplaced.push((
+                hunk.location(),
+                format!(
+                    "could not be matched against {}",
+                    describe_commit(&target_commit)
+                ),
+            ));
+            continue;
+        }
+
+        targets.push(AbsorbTarget {
+            commit,
+            path,
+            start,
+            hunk,
+        });
+    }
+
+    Ok(targets)
+}
+
+/// Returns the lines of `path` in `commit`, each keeping its line ending.
+fn read_blob_lines(repo: &Repository, commit: &git2::Commit, path: &Path) -> Result<Vec<Vec<u8>>> {
+    let entry = commit
+        .tree()
+        .and_then(|tree| tree.get_path(path))
+        .with_context(|| {
+            format!(
+                "failed to find {} in {}",
+                path.display(),
+                describe_commit(commit)
+            )
+        })?;
+    let blob = repo
+        .find_blob(entry.id())
+        .with_context(|| format!("failed to read {}", path.display()))?;
+    Ok(blob
+        .content()
+        .split_inclusive(|byte| *byte == b'\n')
+        .map(<[u8]>::to_vec)
+        .collect())
+}
+
+/// Returns the tree of `commit` with every target's hunk applied.
+fn absorb_into_tree(
+    repo: &Repository,
+    commit: &git2::Commit,
+    targets: &[&AbsorbTarget],
+) -> Result<git2::Oid> {
+    let tree = commit.tree().context("failed to read commit tree")?;
+    let mut index = git2::Index::new().context("failed to create in-memory index")?;
+    index
+        .read_tree(&tree)
+        .context("failed to read commit tree")?;
+
+    let mut paths: Vec<&Path> = targets.iter().map(|target| target.path.as_path()).collect();
+    paths.dedup();
+    for path in paths {
+        let mut lines = read_blob_lines(repo, commit, path)?;
+        let mut edits: Vec<&&AbsorbTarget> = targets
+            .iter()
+            .filter(|target| target.path == path)
+            .collect();
+        // Apply from the bottom of the file up so earlier line numbers stay valid
+        edits.sort_by_key(|edit| std::cmp::Reverse(edit.start));
+        for edit in edits {
+            lines.splice(
+                edit.start..edit.start + edit.hunk.old_lines.len(),
+                edit.hunk.new_lines.iter().cloned(),
+            );
+        }
+
+        let mut entry = index.get_path(path, 0).with_context(|| {
+            format!(
+                "failed to find {} in {}",
+                path.display(),
+                describe_commit(commit)
+            )
+        })?;
+        let contents = lines.concat();
+        entry.id = repo
+            .blob(&contents)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+        entry.file_size = contents.len() as u32;
+        index
+            .add(&entry)
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    index.write_tree_to(repo).context("failed to write tree")
+}
+
+/// Recreates `commit` with `tree` on top of `onto`, carrying over whatever
+/// changed underneath it. Returns the commit itself when nothing changed and
+/// `None` on conflicts.
+fn replay_commit(
+    repo: &Repository,
+    commit: &git2::Commit,
+    onto: git2::Oid,
+    tree: git2::Oid,
+    committer: &git2::Signature,
+    signer: Option<&CommitSigner>,
+) -> Result<Option<git2::Oid>> {
+    let parent = commit
+        .parent_id(0)
+        .context("failed to find parent commit")?;
+    if onto == parent && tree == commit.tree_id() {
+        return Ok(Some(commit.id()));
+    }
+
+    match replay_tree(repo, commit, onto, tree)? {
+        Some(tree) => recommit(repo, commit, onto, tree, committer, signer).map(Some),
+        None => Ok(None),
+    }
+}
+
+/// The tree `commit` would have on top of `onto` if it recorded `tree`, or
+/// `None` on conflicts.
+fn replay_tree(
+    repo: &Repository,
+    commit: &git2::Commit,
+    onto: git2::Oid,
+    tree: git2::Oid,
+) -> Result<Option<git2::Oid>> {
+    let parent = commit
+        .parent_id(0)
+        .context("failed to find parent commit")?;
+    if onto == parent {
+        return Ok(Some(tree));
+    }
+
+    let parent_tree = repo
+        .find_commit(parent)
+        .context("failed to find parent commit")?
+        .tree_id();
+    let onto_tree = repo
+        .find_commit(onto)
+        .context("failed to find parent commit")?
+        .tree_id();
+    merge_tree_ids(repo, parent_tree, onto_tree, tree)
+}
+
+/// Writes a copy of `commit` with `tree` on top of `onto`, keeping its author
+/// and message.
+fn recommit(
+    repo: &Repository,
+    commit: &git2::Commit,
+    onto: git2::Oid,
+    tree: git2::Oid,
+    committer: &git2::Signature,
+    signer: Option<&CommitSigner>,
+) -> Result<git2::Oid> {
+    let onto = repo.find_commit(onto).context("failed to find parent commit")?;
+    let tree = repo.find_tree(tree).context("failed to find tree")?;
+    write_commit(
+        repo,
+        signer,
+        &commit.author(),
+        committer,
+        &String::from_utf8_lossy(commit.message_bytes()),
+        &tree,
+        &[&onto],
+    )
+    .context("failed to write rewritten commit")
+}
+
+/// Three-way merges the trees `ours` and `theirs`, returning `None` on conflicts.
+fn merge_tree_ids(
+    repo: &Repository,
+    ancestor: git2::Oid,
+    ours: git2::Oid,
+    theirs: git2::Oid,
+) -> Result<Option<git2::Oid>> {
+    let ancestor = repo.find_tree(ancestor).context("failed to find tree")?;
+    let ours = repo.find_tree(ours).context("failed to find tree")?;
+    let theirs = repo.find_tree(theirs).context("failed to find tree")?;
+    let mut index = repo
+        .merge_trees(&ancestor, &ours, &theirs, None)
+        .context("failed to merge trees")?;
+    if index.has_conflicts() {
+        return Ok(None);
+    }
+    index
+        .write_tree_to(repo)
+        .map(Some)
+        .context("failed to write tree")
+}
+
+fn handle_move(args: MoveArgs) -> Result<()> {
+    let repo =
+        Repository::discover(".").context("`pk move` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    if args.continue_rebase && args.abort {
+        bail!("Cannot use --continue and --abort together.");
+    }
+
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    if args.continue_rebase {
+        return continue_operation(&repo, &repo_root, &metadata, OperationKind::Move);
+    }
+
+    if args.abort {
+        return abort_operation(&repo_root, OperationKind::Move);
+    }
+
+    ensure_no_active_operation(&repo_root)?;
+
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if !head.is_branch() {
+        bail!("HEAD is not currently on a branch");
+    }
+    let current_branch = head
+        .shorthand()
+        .ok_or_else(|| anyhow!("unable to get current branch name"))?
+        .to_string();
 
-    if args.amend {
-        // Amend the last commit
-        let head_commit = head.peel_to_commit()
-            .context("failed to get HEAD commit")?;
+    let from = args.from.unwrap_or_else(|| current_branch.clone());
+    let to = args.to.ok_or_else(|| {
+        anyhow!("Specify the branch to move the commits to with `--to <branch>`.")
+    })?;
+    if from == to {
+        bail!("Cannot move commits from '{}' onto itself", from);
+    }
+    for branch in [&from, &to] {
+        if !metadata.branches.contains_key(branch) {
+            bail!("Branch '{}' is not tracked by Pancake", branch);
+        }
+    }
 
-        // Get the current index tree
-        let mut index = repo.index().context("failed to get repository index")?;
-        let tree_oid = index.write_tree().context("failed to write tree")?;
-        let tree = repo.find_tree(tree_oid).context("failed to find tree")?;
-
-        // Amend the commit
-        head_commit.amend(
-            Some("HEAD"),
-            Some(&signature),
-            Some(&signature),
-            None,
-            Some(&message),
-            Some(&tree),
-        ).context("failed to amend commit")?;
+    if has_uncommitted_changes(&repo)? {
+        bail!("You have uncommitted changes. Commit or stash them before moving commits.");
+    }
 
-        println!("Amended commit on branch '{}'", current_branch);
+    // Only the branches between the source and the target have their own
+    // commits rewritten; everything stacked on top of them is restacked after
+    let chains = if collect_branch_sequence(&metadata, &to).contains(&from) {
+        vec![BranchChain::collect(&repo, &metadata, Some(&to), &from)?]
+    } else if collect_branch_sequence(&metadata, &from).contains(&to) {
+        vec![BranchChain::collect(&repo, &metadata, Some(&from), &to)?]
     } else {
-        // Create a new commit
-        let mut index = repo.index().context("failed to get repository index")?;
-        let tree_oid = index.write_tree().context("failed to write tree")?;
-        let tree = repo.find_tree(tree_oid).context("failed to find tree")?;
-
-        // Get the parent commit (HEAD)
-        let parent_commit = head.peel_to_commit()
-            .context("failed to get parent commit")?;
-
-        // Create the commit
-        repo.commit(
-            Some("HEAD"),
-            &signature,
-            &signature,
-            &message,
-            &tree,
-            &[&parent_commit],
-        ).context("failed to create commit")?;
+        vec![
+            BranchChain::collect(&repo, &metadata, Some(&from), &from)?,
+            BranchChain::collect(&repo, &metadata, Some(&to), &to)?,
+        ]
+    };
+    let chain_branches: Vec<&String> = chains
+        .iter()
+        .flat_map(|chain| chain.branches.iter().map(|(branch, _)| branch))
+        .collect();
+    if let Some(frozen) = chain_branches
+        .iter()
+        .find(|branch| metadata.is_frozen(branch))
+    {
+        bail!("Cannot move commits because branch '{}' is frozen.", frozen);
+    }
 
-        println!("Created commit on branch '{}'", current_branch);
+    let source_commits = chains
+        .iter()
+        .flat_map(|chain| chain.branches.iter())
+        .find(|(branch, _)| *branch == from)
+        .map(|(_, commits)| commits.as_slice())
+        .unwrap_or_default();
+    if source_commits.is_empty() {
+        bail!("Branch '{}' has no commits of its own to move", from);
     }
+    let selected = select_commits(&repo, &from, source_commits, &args.commits)?;
+    let moved: Vec<&git2::Commit> = source_commits
+        .iter()
+        .filter(|commit| selected.contains(&commit.id()))
+        .collect();
+
+    let committer = repo.signature().context(
+        "failed to get git signature. Ensure git user.name and user.email are configured.",
+    )?;
+    let signer = CommitSigner::resolve(&repo, None, None)?;
+    let mut new_tips = Vec::new();
+    for chain in &chains {
+        let mut tip = chain.base;
+        for (branch, commits) in &chain.branches {
+            let old_tip = resolve_branch_commit(&repo, branch)?.id();
+            let mut replay = commits
+                .iter()
+                .filter(|commit| !selected.contains(&commit.id()))
+                .collect::<Vec<_>>();
+            if *branch == to {
+                replay.extend(moved.iter().copied());
+            }
 
-    Ok(())
+            for commit in replay {
+                tip = replay_commit(
+                    &repo,
+                    commit,
+                    tip,
+                    commit.tree_id(),
+                    &committer,
+                    signer.as_ref(),
+                )?
+                .ok_or_else(
+                    || {
+                        anyhow!(
+                            "Moving would conflict with {} on '{}'; nothing was changed.",
+                            describe_commit(commit),
+                            branch
+                        )
+                    },
+                )?;
+            }
+            if tip != old_tip {
+                new_tips.push((branch.clone(), tip));
+            }
+        }
+    }
+
+    let restack = branches_stacked_on(&metadata, &chain_branches);
+    let mut state = PendingOperation::new(OperationKind::Move, restack, current_branch.clone());
+    state.record_onto_bases(&repo, &metadata)?;
+
+    // Move the checked-out branch first: its safe checkout is the only step
+    // that can still refuse, and nothing has been rewritten yet if it does.
+    new_tips.sort_by_key(|(branch, _)| *branch != current_branch);
+    for (branch, tip) in &new_tips {
+        move_branch(&repo, branch, *tip, "pk move").with_context(|| {
+            if *branch == current_branch {
+                "Moving would overwrite files in the working tree; nothing was changed.".to_string()
+            } else {
+                format!("failed to update branch '{}'", branch)
+            }
+        })?;
+    }
+
+    println!(
+        "Moved {} commit(s) from '{}' to '{}'",
+        moved.len(),
+        from,
+        to
+    );
+    record_change_ids(&repo, &repo_root, &metadata)?;
+
+    if state.branches.is_empty() {
+        return Ok(());
+    }
+    execute_operation(&repo, &repo_root, &metadata, state)
+}
+
+/// Returns the branches stacked on top of `rewritten` that are not part of it,
+/// parents first. Only their own commits need replaying onto the new tips.
+fn branches_stacked_on(metadata: &StackMetadata, rewritten: &[&String]) -> Vec<String> {
+    let mut branches = Vec::new();
+    for branch in rewritten {
+        for child in metadata.get_children(branch) {
+            if rewritten.contains(&&child) {
+                continue;
+            }
+            branches.extend(collect_branch_sequence(metadata, &child));
+        }
+    }
+    branches
+}
+
+/// Resolves `revs` to commits among `candidates` (the commits of `branch`), or
+/// asks which ones to take when none are given. Returns them oldest first.
+fn select_commits(
+    repo: &Repository,
+    branch: &str,
+    candidates: &[git2::Commit],
+    revs: &[String],
+) -> Result<Vec<git2::Oid>> {
+    if !revs.is_empty() {
+        let mut selected = Vec::new();
+        for rev in revs {
+            let commit = repo
+                .revparse_single(rev)
+                .and_then(|object| object.peel_to_commit())
+                .with_context(|| format!("'{}' does not name a commit", rev))?;
+            if !candidates
+                .iter()
+                .any(|candidate| candidate.id() == commit.id())
+            {
+                bail!(
+                    "Commit {} does not belong to branch '{}'",
+                    describe_commit(&commit),
+                    branch
+                );
+            }
+            selected.push(commit.id());
+        }
+        return Ok(candidates
+            .iter()
+            .map(|candidate| candidate.id())
+            .filter(|id| selected.contains(id))
+            .collect());
+    }
+
+    let items: Vec<String> = candidates.iter().map(describe_commit).collect();
+    if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+        let selection = dialoguer::MultiSelect::new()
+            .with_prompt(format!("Commits to move from '{}'", branch))
+            .items(&items)
+            .interact_opt()
+            .context("failed to read selection")?;
+        return match selection {
+            Some(indices) if !indices.is_empty() => Ok(indices
+                .into_iter()
+                .map(|idx| candidates[idx].id())
+                .collect()),
+            _ => bail!("No commits selected"),
+        };
+    }
+
+    println!("Commits on '{}':", branch);
+    for item in &items {
+        println!("  {}", item);
+    }
+    bail!(
+        "No commits given and no terminal is attached.\nPass the commits to move, e.g. `pk move <commit> --to <branch>`."
+    );
+}
+
+fn handle_autosquash(args: AutosquashArgs) -> Result<()> {
+    let repo =
+        Repository::discover(".").context("`pk autosquash` must be run inside a Git repository")?;
+    let workdir = repo
+        .workdir()
+        .context("bare repositories are not supported by Pancake")?;
+    let repo_root = workdir.to_path_buf();
+
+    let config_path = repo_root.join(".pancake/config");
+    if !config_path.exists() {
+        bail!("Pancake is not initialized. Run `pk init` first.");
+    }
+
+    if args.continue_rebase && args.abort {
+        bail!("Cannot use --continue and --abort together.");
+    }
+
+    let metadata = StackMetadata::load(&repo_root)?;
+
+    if args.continue_rebase {
+        return continue_operation(&repo, &repo_root, &metadata, OperationKind::Autosquash);
+    }
+
+    if args.abort {
+        return abort_operation(&repo_root, OperationKind::Autosquash);
+    }
+
+    ensure_no_active_operation(&repo_root)?;
+
+    let head = repo.head().context("unable to resolve current HEAD")?;
+    if !head.is_branch() {
+        bail!("HEAD is not currently on a branch");
+    }
+    let current_branch = head
+        .shorthand()
+        .ok_or_else(|| anyhow!("unable to get current branch name"))?
+        .to_string();
+    if !metadata.branches.contains_key(&current_branch) {
+        bail!(
+            "Current branch '{}' is not tracked by Pancake",
+            current_branch
+        );
+    }
+
+    let chain = BranchChain::collect(&repo, &metadata, None, &current_branch)?;
+
+    // Pair every fixup!/squash! commit with the latest commit below it that it names
+    let commits: Vec<&git2::Commit> = chain
+        .branches
+        .iter()
+        .flat_map(|(_, commits)| commits.iter())
+        .collect();
+    let mut folds: Vec<(git2::Oid, &git2::Commit)> = Vec::new();
+    for (idx, commit) in commits.iter().enumerate() {
+        let Some(subject) = fold_subject(commit) else {
+            continue;
+        };
+        let target = commits[..idx].iter().rev().find(|candidate| {
+            fold_subject(candidate).is_none()
+                && (candidate.summary() == Some(subject)
+                    || (subject.len() >= 4 && candidate.id().to_string().starts_with(subject)))
+        });
+        match target {
+            Some(target) => folds.push((target.id(), commit)),
+            None => println!(
+                "Could not find the commit {} refers to; leaving it in place",
+                describe_commit(commit)
+            ),
+        }
+    }
+    if folds.is_empty() {
+        println!("No fixup commits to fold.");
+        return Ok(());
+    }
+
+    let lowest = folds
+        .iter()
+        .map(|(target, _)| chain.branch_index(*target))
+        .min()
+        .unwrap_or(0);
+    if let Some((frozen, _)) = chain.branches[lowest..]
+        .iter()
+        .find(|(branch, _)| metadata.is_frozen(branch))
+    {
+        bail!(
+            "Cannot fold fixups into '{}' without rewriting frozen branch '{}'.",
+            chain.branches[lowest].0,
+            frozen
+        );
+    }
+
+    let committer = repo.signature().context(
+        "failed to get git signature. Ensure git user.name and user.email are configured.",
+    )?;
+    let signer = CommitSigner::resolve(&repo, None, None)?;
+    let folded: Vec<git2::Oid> = folds.iter().map(|(_, fixup)| fixup.id()).collect();
+    let mut new_tips = Vec::new();
+    let mut tip = chain.base;
+    for (branch, commits) in &chain.branches {
+        let old_tip = resolve_branch_commit(&repo, branch)?.id();
+        for commit in commits {
+            if folded.contains(&commit.id()) {
+                continue;
+            }
+
+            let original_message = String::from_utf8_lossy(commit.message_bytes()).to_string();
+            let mut message = original_message.clone();
+            let mut tree = commit.tree_id();
+            for (_, fixup) in folds.iter().filter(|(target, _)| *target == commit.id()) {
+                let parent_tree = fixup
+                    .parent(0)
+                    .context("failed to find parent commit")?
+                    .tree_id();
+                tree = merge_tree_ids(&repo, parent_tree, tree, fixup.tree_id())?.ok_or_else(
+                    || {
+                        anyhow!(
+                            "Folding {} into {} would conflict; nothing was changed.",
+                            describe_commit(fixup),
+                            describe_commit(commit)
+                        )
+                    },
+                )?;
+
+                if fixup
+                    .summary()
+                    .is_some_and(|summary| summary.starts_with("squash! "))
+                {
+                    let body = fixup
+                        .message()
+                        .and_then(|message| message.split_once('\n'))
+                        .map(|(_, body)| body.trim())
+                        .unwrap_or("");
+                    if !body.is_empty() {
+                        message = format!("{}\n\n{}\n", message.trim_end(), body);
+                    }
+                }
+            }
+
+            tip = replay_commit(&repo, commit, tip, tree, &committer, signer.as_ref())?.ok_or_else(|| {
+                anyhow!(
+                    "Replaying {} on '{}' would conflict; nothing was changed.",
+                    describe_commit(commit),
+                    branch
+                )
+            })?;
+            if message != original_message {
+                let rewritten = repo.find_commit(tip).context("failed to find commit")?;
+                let tree = rewritten.tree().context("failed to find tree")?;
+                let parents: Vec<git2::Commit> = rewritten.parents().collect();
+                let parents: Vec<&git2::Commit> = parents.iter().collect();
+                tip = write_commit(
+                    &repo,
+                    signer.as_ref(),
+                    &rewritten.author(),
+                    &committer,
+                    &message,
+                    &tree,
+                    &parents,
+                )
+                .context("failed to write squashed commit")?;
+            }
+        }
+        if tip != old_tip {
+            new_tips.push((branch.clone(), tip));
+        }
+    }
+
+    let rewritten: Vec<&String> = chain.branches[lowest..]
+        .iter()
+        .map(|(branch, _)| branch)
+        .collect();
+    let restack = branches_stacked_on(&metadata, &rewritten);
+    let mut state =
+        PendingOperation::new(OperationKind::Autosquash, restack, current_branch.clone());
+    state.record_onto_bases(&repo, &metadata)?;
+
+    for (branch, tip) in &new_tips {
+        repo.reference(
+            &format!("refs/heads/{}", branch),
+            *tip,
+            true,
+            "pk autosquash",
+        )
+        .with_context(|| format!("failed to update branch '{}'", branch))?;
+    }
+
+    for (target, fixup) in &folds {
+        let target = repo.find_commit(*target).context("failed to find commit")?;
+        println!(
+            "Folded {} into {}",
+            describe_commit(fixup),
+            describe_commit(&target)
+        );
+    }
+    record_change_ids(&repo, &repo_root, &metadata)?;
+
+    if state.branches.is_empty() {
+        return Ok(());
+    }
+    if has_uncommitted_changes(&repo)? {
+        run_git_checked(&repo_root, &["stash", "push", "-m", "pk autosquash"])?;
+        state.autostash = true;
+    }
+    execute_operation(&repo, &repo_root, &metadata, state)
+}
+
+/// Returns the subject a `fixup!` or `squash!` commit refers to, with nested
+/// prefixes removed, or `None` for ordinary commits.
+fn fold_subject<'a>(commit: &'a git2::Commit) -> Option<&'a str> {
+    let mut subject = commit.summary()?;
+    let mut is_fold = false;
+    while let Some(rest) = subject
+        .strip_prefix("fixup! ")
+        .or_else(|| subject.strip_prefix("squash! "))
+    {
+        subject = rest;
+        is_fold = true;
+    }
+    is_fold.then_some(subject)
+}
+
+/// Resolves the target of `pk commit --fixup/--squash`, which must be one of the
+/// commits of `branch` or of the branches below it.
+fn resolve_fold_target<'repo>(
+    repo: &'repo Repository,
+    metadata: &StackMetadata,
+    branch: &str,
+    rev: &str,
+) -> Result<git2::Commit<'repo>> {
+    let commit = repo
+        .revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .with_context(|| format!("'{}' does not name a commit", rev))?;
+    if !metadata.branches.contains_key(branch) {
+        bail!("Current branch '{}' is not tracked by Pancake", branch);
+    }
+
+    let chain = BranchChain::collect(repo, metadata, None, branch)?;
+    let owner = chain.owner(commit.id()).ok_or_else(|| {
+        anyhow!(
+            "Commit {} is not part of the stack below '{}'",
+            describe_commit(&commit),
+            branch
+        )
+    })?;
+    if metadata.is_frozen(owner) {
+        bail!(
+            "Commit {} is on frozen branch '{}'",
+            describe_commit(&commit),
+            owner
+        );
+    }
+    Ok(commit)
 }
 
 fn handle_sync(args: SyncArgs) -> Result<()> {
@@ -755,11 +4025,11 @@ fn handle_sync(args: SyncArgs) -> Result<()> {
         bail!("Cannot use --continue and --abort together.");
     }
 
-    if (args.continue_rebase || args.abort) && (args.all || args.from_main) {
-        bail!("Cannot combine --continue/--abort with --all/--from-main.");
+    if (args.continue_rebase || args.abort) && (args.all || args.from_main || args.fetch) {
+        bail!("Cannot combine --continue/--abort with --all/--from-main/--fetch.");
     }
 
-    let metadata = StackMetadata::load(&repo_root)?;
+    let mut metadata = StackMetadata::load(&repo_root)?;
 
     if args.continue_rebase {
         return continue_operation(&repo, &repo_root, &metadata, OperationKind::Sync);
@@ -787,7 +4057,17 @@ fn handle_sync(args: SyncArgs) -> Result<()> {
         );
     }
 
-    let start_branch = if args.all || args.from_main {
+    let config = PancakeConfig::load(&repo_root)?;
+    let fetch = if args.no_fetch || args.dry_run {
+        false
+    } else {
+        args.fetch || config.sync.fetch
+    };
+    if fetch {
+        update_trunk(&repo, &repo_root, &config)?;
+    }
+
+    let start_branch = if args.all || args.from_main || fetch {
         metadata.find_stack_bottom(&current_branch)
     } else {
         current_branch.clone()
@@ -798,10 +4078,267 @@ fn handle_sync(args: SyncArgs) -> Result<()> {
         bail!("No tracked branches to sync starting from '{}'", start_branch);
     }
 
-    let state = PendingOperation::new(OperationKind::Sync, branches, current_branch);
+    // Branches whose changes already landed in trunk drop out of the stack; their
+    // children only replay the commits after the merged branch's tip
+    let trunk = &config.repository.main_branch;
+    let trunk_tips = trunk_tips(&repo, &config);
+    let mut merged = Vec::new();
+    let mut merged_bases = HashMap::new();
+    let mut reparented = HashMap::new();
+    for branch in collect_branch_sequence(&metadata, &metadata.find_stack_bottom(&current_branch)) {
+        if !is_merged_into_trunk(&repo, &metadata, &branch, &trunk_tips)? {
+            continue;
+        }
+        let tip = resolve_branch_commit(&repo, &branch)?.id();
+        let parent = metadata.get_parent(&branch);
+        println!("Branch '{}' is merged into '{}'", branch, trunk);
+        let mut children = metadata.get_children(&branch);
+        children.sort();
+        for child in children {
+            metadata.update_parent(&child, parent.clone());
+            println!(
+                "{} '{}' onto '{}'",
+                if args.dry_run {
+                    "Would reparent"
+                } else {
+                    "Reparented"
+                },
+                child,
+                parent.as_deref().unwrap_or(trunk)
+            );
+            merged_bases.insert(child.clone(), tip.to_string());
+            reparented.insert(child, branch.clone());
+        }
+        merged.push(branch);
+    }
+    let branches: Vec<String> = branches
+        .into_iter()
+        .filter(|branch| !merged.contains(branch))
+        .collect();
+
+    let mut state = PendingOperation::new(OperationKind::Sync, branches, current_branch.clone());
+    state.record_onto_bases(&repo, &metadata)?;
+    state.onto_bases.extend(merged_bases);
+    state.reparented = reparented;
+    if args.dry_run {
+        return simulate_operation(&repo_root, &metadata, &state);
+    }
+    metadata.save(&repo_root)?;
+
+    for branch in &merged {
+        if *branch == current_branch {
+            println!(
+                "Switch away from '{}' and delete it with `pk branch delete {}`",
+                branch, branch
+            );
+            continue;
+        }
+        let delete = if args.delete_merged {
+            true
+        } else if std::io::stdin().is_terminal() && std::io::stderr().is_terminal() {
+            dialoguer::Confirm::new()
+                .with_prompt(format!("Delete merged branch '{}'?", branch))
+                .default(true)
+                .interact()
+                .context("failed to read confirmation")?
+        } else {
+            println!(
+                "Delete it with `pk branch delete {}` or `pk sync --delete-merged`",
+                branch
+            );
+            false
+        };
+        if delete {
+            delete_merged_branch(&repo, &repo_root, &mut metadata, branch)?;
+        }
+    }
+
     execute_operation(&repo, &repo_root, &metadata, state)
 }
 
+/// Fetches the configured remote and fast-forwards the main branch to its
+/// remote-tracking branch. A diverged main branch is reported and left alone.
+fn update_trunk(repo: &Repository, repo_root: &Path, config: &PancakeConfig) -> Result<()> {
+    let trunk = &config.repository.main_branch;
+    let remote = &config.repository.remote;
+    if repo.find_remote(remote).is_err() {
+        bail!(
+            "Remote '{}' does not exist; add it or change `repository.remote` in .pancake/config",
+            remote
+        );
+    }
+
+    println!("Fetching '{}'", remote);
+    run_git_checked(repo_root, &["fetch", remote])?;
+
+    let tracking = format!("{}/{}", remote, trunk);
+    let Ok(remote_tip) = resolve_branch_commit(repo, &tracking).map(|commit| commit.id()) else {
+        println!("'{}' does not exist; using local '{}'", tracking, trunk);
+        return Ok(());
+    };
+    let local_tip = resolve_branch_commit(repo, trunk)
+        .with_context(|| format!("failed to resolve main branch '{}'", trunk))?
+        .id();
+
+    if local_tip == remote_tip || repo.graph_descendant_of(local_tip, remote_tip)? {
+        println!("'{}' is up to date with '{}'", trunk, tracking);
+    } else if repo.graph_descendant_of(remote_tip, local_tip)? {
+        move_branch(
+            repo,
+            trunk,
+            remote_tip,
+            &format!("pk sync: fast-forward to {}", tracking),
+        )?;
+        let remote_id = remote_tip.to_string();
+        println!(
+            "Fast-forwarded '{}' to '{}' ({})",
+            trunk,
+            tracking,
+            &remote_id[..7]
+        );
+    } else {
+        eprintln!(
+            "Warning: '{}' has diverged from '{}'; restacking onto local '{}'. Reconcile them with `git pull --rebase` on '{}'.",
+            trunk, tracking, trunk, trunk
+        );
+    }
+    Ok(())
+}
+
+/// The main branch and its remote-tracking branch, whichever exist.
+fn trunk_tips(repo: &Repository, config: &PancakeConfig) -> Vec<git2::Oid> {
+    let trunk = &config.repository.main_branch;
+    [
+        trunk.clone(),
+        format!("{}/{}", config.repository.remote, trunk),
+    ]
+    .iter()
+    .filter_map(|name| resolve_branch_commit(repo, name).ok())
+    .map(|commit| commit.id())
+    .collect()
+}
+
+/// Whether every change `branch` makes on top of its parent is already in
+/// one of `trunk_tips`: its commits are reachable from trunk, were applied
+/// with the same patch ids, or squash-merged so applying them changes nothing.
+fn is_merged_into_trunk(
+    repo: &Repository,
+    metadata: &StackMetadata,
+    branch: &str,
+    trunk_tips: &[git2::Oid],
+) -> Result<bool> {
+    let tip = resolve_branch_commit(repo, branch)?.id();
+    let contains_tip =
+        |commit: git2::Oid| commit == tip || repo.graph_descendant_of(commit, tip).unwrap_or(false);
+    // Once merged, the parent holds the branch's commits, so look for where it started
+    let fork_point = metadata
+        .get_parent(branch)
+        .and_then(|parent| resolve_branch_commit(repo, &parent).ok())
+        .map(|commit| commit.id())
+        .filter(|parent_tip| !contains_tip(*parent_tip))
+        .or_else(|| branch_creation_point(repo, branch));
+    let Some(fork_point) = fork_point else {
+        return Ok(false);
+    };
+    let own = unmerged_commits(repo, tip, &[fork_point])?;
+    // A branch without commits of its own has nothing to merge
+    let Some(oldest) = own.last() else {
+        return Ok(false);
+    };
+    if unmerged_commits(repo, tip, trunk_tips)?.is_empty() {
+        return Ok(true);
+    }
+
+    let base_tree = match oldest.parent(0) {
+        Ok(parent) => parent.tree_id(),
+        Err(_) => return Ok(false),
+    };
+    let tip_tree = repo.find_commit(tip).context("failed to find commit")?.tree_id();
+    for &trunk_tip in trunk_tips {
+        let trunk_tree = repo
+            .find_commit(trunk_tip)
+            .context("failed to find commit")?
+            .tree_id();
+        if merge_tree_ids(repo, base_tree, trunk_tree, tip_tree)? == Some(trunk_tree) {
+            return Ok(true);
+        }
+    }
+
+    // Patch ids need a diff per commit, so only trunk commits after the merge
+    // base are compared, stopping once every commit of the branch is found
+    let mut patch_ids = Vec::new();
+    for commit in &own {
+        patch_ids.push(commit_patch_id(repo, commit)?);
+    }
+    for &trunk_tip in trunk_tips {
+        let mut hidden = vec![tip];
+        hidden.extend(repo.merge_base(trunk_tip, tip).ok());
+        let mut missing = patch_ids.clone();
+        for commit in unmerged_commits(repo, trunk_tip, &hidden)? {
+            if commit.parent_count() != 1 {
+                continue;
+            }
+            let patch_id = commit_patch_id(repo, &commit)?;
+            missing.retain(|id| *id != patch_id);
+            if missing.is_empty() {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+/// The commit `branch` was created at, from the oldest entry of its reflog.
+fn branch_creation_point(repo: &Repository, branch: &str) -> Option<git2::Oid> {
+    let reflog = repo.reflog(&format!("refs/heads/{}", branch)).ok()?;
+    reflog
+        .get(reflog.len().checked_sub(1)?)
+        .map(|entry| entry.id_new())
+        .filter(|oid| !oid.is_zero())
+}
+
+/// Identifies a commit by its changes, like `git patch-id`.
+fn commit_patch_id(repo: &Repository, commit: &git2::Commit) -> Result<git2::Oid> {
+    let parent_tree = match commit.parent(0) {
+        Ok(parent) => Some(parent.tree().context("failed to find tree")?),
+        Err(_) => None,
+    };
+    let tree = commit.tree().context("failed to find tree")?;
+    repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+        .and_then(|diff| diff.patchid(None))
+        .with_context(|| format!("failed to compute the patch id of {}", describe_commit(commit)))
+}
+
+/// Deletes a branch `pk sync` found merged, recording its tip so it can be
+/// restored like `pk branch delete` does.
+fn delete_merged_branch(
+    repo: &Repository,
+    repo_root: &Path,
+    metadata: &mut StackMetadata,
+    branch_name: &str,
+) -> Result<()> {
+    let mut branch = repo
+        .find_branch(branch_name, BranchType::Local)
+        .with_context(|| format!("unable to find branch '{}'", branch_name))?;
+    let tip = branch
+        .get()
+        .peel_to_commit()
+        .with_context(|| format!("unable to get commit for branch '{}'", branch_name))?
+        .id();
+
+    branch
+        .delete()
+        .with_context(|| format!("failed to delete branch '{}'", branch_name))?;
+
+    let mut deleted_log = DeletedBranchLog::load(repo_root)?;
+    deleted_log.record(branch_name, tip, metadata.get_parent(branch_name));
+    deleted_log.save(repo_root)?;
+    metadata.remove_branch(branch_name);
+    metadata.save(repo_root)?;
+    println!("Deleted merged branch '{}'", branch_name);
+    Ok(())
+}
+
 fn handle_restack(args: RestackArgs) -> Result<()> {
     let repo = Repository::discover(".").context("`pk restack` must be run inside a Git repository")?;
     let workdir = repo
@@ -855,16 +4392,128 @@ fn handle_restack(args: RestackArgs) -> Result<()> {
         );
     }
 
-    let state = PendingOperation::new(OperationKind::Restack, branches, current_branch);
-    execute_operation(&repo, &repo_root, &metadata, state)
+    let state = PendingOperation::new(OperationKind::Restack, branches, current_branch);
+    if args.dry_run {
+        return simulate_operation(&repo_root, &metadata, &state);
+    }
+    execute_operation(&repo, &repo_root, &metadata, state)
+}
+
+fn checkout_branch(
+    repo: &Repository,
+    branch_name: &str,
+    options: &CheckoutOptions,
+) -> Result<()> {
+    let previous = repo
+        .head()
+        .ok()
+        .filter(|head| head.is_branch())
+        .and_then(|head| head.shorthand().map(|s| s.to_string()));
+
+    // Stashing needs a mutable handle, which callers holding references into
+    // `repo` cannot give us, so use a separate one.
+    let mut stash_repo = if options.autostash && has_uncommitted_changes(repo)? {
+        let mut stash_repo = Repository::open(repo.path())
+            .context("failed to open repository for stashing")?;
+        let signature = stash_repo.signature()
+            .context("failed to get git signature. Ensure git user.name and user.email are configured.")?;
+        let message = format!("pk autostash before checking out '{}'", branch_name);
+        stash_repo.stash_save(&signature, &message, None)
+            .context("failed to stash uncommitted changes")?;
+        println!("Stashed uncommitted changes");
+        Some(stash_repo)
+    } else {
+        None
+    };
+
+    if let Err(err) = switch_worktree(repo, branch_name, options.force) {
+        if let Some(stash_repo) = stash_repo.as_mut() {
+            stash_repo.stash_pop(0, None)
+                .context("failed to restore stashed changes; they remain in `git stash list`")?;
+        }
+        return Err(err);
+    }
+
+    if let Some(stash_repo) = stash_repo.as_mut() {
+        // The checkout went through `repo`, so reload the index this handle cached
+        stash_repo
+            .index()
+            .and_then(|mut index| index.read(true))
+            .context("failed to reload repository index")?;
+        match stash_repo.stash_pop(0, None) {
+            Ok(()) => println!("Re-applied stashed changes"),
+            Err(err) => println!(
+                "Warning: could not re-apply stashed changes ({}). They remain in `git stash list`.",
+                err.message()
+            ),
+        }
+    }
+
+    // Remember where we came from so `pk co -` can return there
+    if let (Some(previous), Some(workdir)) = (previous, repo.workdir())
+        && previous != branch_name
+    {
+        let mut navigation = NavigationState::load(workdir)?;
+        navigation.record_visit(previous);
+        navigation.save(workdir)?;
+    }
+    Ok(())
+}
+
+/// Updates the working tree to `branch_name` and only then moves HEAD, so a
+/// refused checkout leaves both untouched.
+fn switch_worktree(repo: &Repository, branch_name: &str, force: bool) -> Result<()> {
+    let refname = format!("refs/heads/{}", branch_name);
+    let target = repo
+        .find_reference(&refname)
+        .and_then(|reference| reference.peel_to_tree())
+        .with_context(|| format!("unable to find branch '{}'", branch_name))?;
+
+    let mut conflicts = Vec::new();
+    let result = {
+        let mut builder = git2::build::CheckoutBuilder::new();
+        if force {
+            builder.force();
+        } else {
+            builder
+                .safe()
+                .notify_on(git2::CheckoutNotificationType::CONFLICT)
+                .notify(|_, path, _, _, _| {
+                    if let Some(path) = path {
+                        conflicts.push(path.display().to_string());
+                    }
+                    true
+                });
+        }
+        repo.checkout_tree(target.as_object(), Some(&mut builder))
+    };
+
+    if let Err(err) = result {
+        if conflicts.is_empty() {
+            return Err(err).with_context(|| format!("failed to checkout branch '{}'", branch_name));
+        }
+        bail!(
+            "Your local changes to the following files would be overwritten by checking out '{}':\n{}\nCommit them first, or use `--autostash` or `--force`.",
+            branch_name,
+            conflicts
+                .iter()
+                .map(|path| format!("  {}", path))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+
+    repo.set_head(&refname)
+        .with_context(|| format!("failed to set HEAD to branch '{}'", branch_name))
 }
 
-fn checkout_branch(repo: &Repository, branch_name: &str) -> Result<()> {
-    repo.set_head(&format!("refs/heads/{}", branch_name))
-        .with_context(|| format!("failed to set HEAD to branch '{}'", branch_name))?;
-    repo.checkout_head(Some(git2::build::CheckoutBuilder::default().force()))
-        .with_context(|| format!("failed to checkout branch '{}'", branch_name))?;
-    Ok(())
+fn has_uncommitted_changes(repo: &Repository) -> Result<bool> {
+    let mut options = git2::StatusOptions::new();
+    options.include_untracked(false).include_ignored(false);
+    let statuses = repo
+        .statuses(Some(&mut options))
+        .context("failed to read working tree status")?;
+    Ok(!statuses.is_empty())
 }
 
 fn detect_main_branch(repo: &Repository) -> Result<String> {
@@ -898,6 +4547,57 @@ fn branch_exists(repo: &Repository, name: &str) -> bool {
     repo.find_branch(name, BranchType::Local).is_ok()
 }
 
+fn remote_branch_exists(repo: &Repository, name: &str) -> bool {
+    repo.find_branch(name, BranchType::Remote).is_ok()
+}
+
+/// Resolves a stack parent, which may be a local branch or a remote-tracking
+/// branch such as `origin/feature-x`, to its tip commit.
+fn resolve_branch_commit<'repo>(
+    repo: &'repo Repository,
+    name: &str,
+) -> Result<git2::Commit<'repo>> {
+    let branch = repo
+        .find_branch(name, BranchType::Local)
+        .or_else(|_| repo.find_branch(name, BranchType::Remote))
+        .with_context(|| format!("unable to find branch '{}'", name))?;
+    branch
+        .get()
+        .peel_to_commit()
+        .with_context(|| format!("unable to get commit for branch '{}'", name))
+}
+
+/// Splits a remote-tracking branch name like `origin/feature-x` into its
+/// remote and the branch name on that remote.
+fn split_remote_branch(repo: &Repository, name: &str) -> Option<RemoteBranch> {
+    if branch_exists(repo, name) || !remote_branch_exists(repo, name) {
+        return None;
+    }
+    let remotes = repo.remotes().ok()?;
+    remotes
+        .iter()
+        .flatten()
+        .filter_map(|remote| {
+            name.strip_prefix(remote)
+                .and_then(|rest| rest.strip_prefix('/'))
+                .map(|branch| RemoteBranch {
+                    remote: remote.to_string(),
+                    name: branch.to_string(),
+                })
+        })
+        .max_by_key(|remote_branch| remote_branch.remote.len())
+}
+
+/// Updates a remote-tracking branch from its remote.
+fn fetch_remote_branch(repo_root: &Path, remote_branch: &RemoteBranch) -> Result<()> {
+    let refspec = format!(
+        "+refs/heads/{}:{}",
+        remote_branch.name,
+        remote_branch.tracking_ref()
+    );
+    run_git_checked(repo_root, &["fetch", &remote_branch.remote, &refspec])
+}
+
 fn display_path(path: &Path) -> String {
     path.display().to_string()
 }
@@ -906,6 +4606,11 @@ fn display_path(path: &Path) -> String {
 enum OperationKind {
     Sync,
     Restack,
+    Amend,
+    Absorb,
+    Move,
+    Autosquash,
+    Commit,
 }
 
 impl OperationKind {
@@ -913,6 +4618,11 @@ impl OperationKind {
         match self {
             OperationKind::Sync => "sync",
             OperationKind::Restack => "restack",
+            OperationKind::Amend => "amend",
+            OperationKind::Absorb => "absorb",
+            OperationKind::Move => "move",
+            OperationKind::Autosquash => "autosquash",
+            OperationKind::Commit => "commit",
         }
     }
 
@@ -920,13 +4630,23 @@ impl OperationKind {
         match self {
             OperationKind::Sync => "pk sync",
             OperationKind::Restack => "pk restack",
+            OperationKind::Amend => "pk amend",
+            OperationKind::Absorb => "pk absorb",
+            OperationKind::Move => "pk move",
+            OperationKind::Autosquash => "pk autosquash",
+            OperationKind::Commit => "pk commit",
         }
     }
 
     fn past_tense(&self) -> &'static str {
         match self {
             OperationKind::Sync => "Synced",
-            OperationKind::Restack => "Restacked",
+            OperationKind::Restack
+            | OperationKind::Amend
+            | OperationKind::Absorb
+            | OperationKind::Move
+            | OperationKind::Autosquash
+            | OperationKind::Commit => "Restacked",
         }
     }
 }
@@ -937,6 +4657,21 @@ struct PendingOperation {
     branches: Vec<String>,
     current_index: usize,
     original_branch: String,
+    /// Commits each branch's own work starts after, recorded when its parent is
+    /// about to be rewritten so only those commits are replayed
+    #[serde(default)]
+    onto_bases: HashMap<String, String>,
+    /// Whether local changes were stashed before the operation started and must
+    /// be restored on the original branch once it finishes
+    #[serde(default)]
+    autostash: bool,
+    /// Branches skipped because they already sat on their parent's tip
+    #[serde(default)]
+    up_to_date: Vec<String>,
+    /// The former parents of branches moved off a merged branch, put back if
+    /// the operation is aborted
+    #[serde(default)]
+    reparented: HashMap<String, String>,
 }
 
 impl PendingOperation {
@@ -946,6 +4681,33 @@ impl PendingOperation {
             branches,
             current_index: 0,
             original_branch,
+            onto_bases: HashMap::new(),
+            autostash: false,
+            up_to_date: Vec::new(),
+            reparented: HashMap::new(),
+        }
+    }
+
+    /// Records every branch's current parent tip as its base, so that after the
+    /// parents are rewritten each branch replays only its own commits.
+    fn record_onto_bases(&mut self, repo: &Repository, metadata: &StackMetadata) -> Result<()> {
+        for branch in &self.branches {
+            let Some(parent) = metadata.get_parent(branch) else {
+                continue;
+            };
+            let base = resolve_branch_commit(repo, &parent)?;
+            self.onto_bases.insert(branch.clone(), base.id().to_string());
+        }
+        Ok(())
+    }
+
+    /// The commit after which `branch`'s own commits start: its recorded base,
+    /// or the tip of the parent it is rebased onto.
+    fn base_for(&self, branch: &str, onto: git2::Oid) -> Result<git2::Oid> {
+        match self.onto_bases.get(branch) {
+            Some(base) => git2::Oid::from_str(base)
+                .with_context(|| format!("invalid base '{}' recorded for '{}'", base, branch)),
+            None => Ok(onto),
         }
     }
 
@@ -999,6 +4761,204 @@ fn ensure_no_active_operation(repo_root: &Path) -> Result<()> {
     Ok(())
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct DeletedBranch {
+    name: String,
+    tip: String,
+    parent: Option<String>,
+    deleted_at: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct DeletedBranchLog {
+    branches: Vec<DeletedBranch>,
+}
+
+impl DeletedBranchLog {
+    fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(".pancake/deleted_branches.json")
+    }
+
+    fn load(repo_root: &Path) -> Result<Self> {
+        let path = Self::path(repo_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", display_path(&path)))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))
+    }
+
+    fn save(&self, repo_root: &Path) -> Result<()> {
+        let path = Self::path(repo_root);
+        let serialized = serde_json::to_string_pretty(self)
+            .context("failed to serialize deleted branch log")?;
+        fs::write(&path, serialized)
+            .with_context(|| format!("failed to write {}", display_path(&path)))
+    }
+
+    fn record(&mut self, name: &str, tip: git2::Oid, parent: Option<String>) {
+        self.branches.push(DeletedBranch {
+            name: name.to_string(),
+            tip: tip.to_string(),
+            parent,
+            deleted_at: chrono::Utc::now().to_rfc3339(),
+        });
+    }
+}
+
+/// Where each `Change-Id` currently lives, so tools can follow a change as
+/// restacks and moves give it new commits and branches.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct ChangeIndex {
+    changes: BTreeMap<String, ChangeLocation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChangeLocation {
+    branch: String,
+    commit: String,
+}
+
+impl ChangeIndex {
+    fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(".pancake/changes.json")
+    }
+
+    /// Scans the commits each tracked branch adds on top of its parent.
+    fn build(repo: &Repository, metadata: &StackMetadata) -> Result<Self> {
+        let mut branches: Vec<_> = metadata.branches.iter().collect();
+        branches.sort_by_key(|(name, _)| name.as_str());
+
+        let mut index = Self::default();
+        for (name, branch) in branches {
+            let Ok(tip) = resolve_branch_commit(repo, name) else {
+                continue;
+            };
+            let mut walk = repo.revwalk().context("failed to walk history")?;
+            walk.push(tip.id()).context("failed to walk history")?;
+            if let Some(parent) = &branch.parent
+                && let Ok(parent) = resolve_branch_commit(repo, parent)
+            {
+                walk.hide(parent.id()).context("failed to walk history")?;
+            }
+            for oid in walk {
+                let oid = oid.context("failed to walk history")?;
+                let commit = repo.find_commit(oid).context("failed to find commit")?;
+                if let Some(id) = change_id(commit.message().unwrap_or("")) {
+                    index.changes.insert(
+                        id.to_string(),
+                        ChangeLocation {
+                            branch: name.clone(),
+                            commit: oid.to_string(),
+                        },
+                    );
+                }
+            }
+        }
+        Ok(index)
+    }
+
+    fn save(&self, repo_root: &Path) -> Result<()> {
+        let path = Self::path(repo_root);
+        let serialized =
+            serde_json::to_string_pretty(self).context("failed to serialize change index")?;
+        fs::write(&path, serialized)
+            .with_context(|| format!("failed to write {}", display_path(&path)))
+    }
+}
+
+/// Rebuilds `.pancake/changes.json` after commits were created or rewritten.
+/// Repositories that never used change IDs don't get the file.
+fn record_change_ids(repo: &Repository, repo_root: &Path, metadata: &StackMetadata) -> Result<()> {
+    let index = ChangeIndex::build(repo, metadata)?;
+    if index.changes.is_empty() && !ChangeIndex::path(repo_root).exists() {
+        return Ok(());
+    }
+    index.save(repo_root)
+}
+
+const NAVIGATION_HISTORY_LIMIT: usize = 20;
+
+/// Per-repository navigation state: the child last chosen at each fork and the
+/// branches recently left through pk, most recent last.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NavigationState {
+    #[serde(default)]
+    fork_choices: HashMap<String, String>,
+    #[serde(default)]
+    history: Vec<String>,
+}
+
+impl NavigationState {
+    fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(".pancake/navigation.json")
+    }
+
+    fn load(repo_root: &Path) -> Result<Self> {
+        let path = Self::path(repo_root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", display_path(&path)))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))
+    }
+
+    fn save(&self, repo_root: &Path) -> Result<()> {
+        let path = Self::path(repo_root);
+        let serialized = serde_json::to_string_pretty(self)
+            .context("failed to serialize navigation state")?;
+        fs::write(&path, serialized)
+            .with_context(|| format!("failed to write {}", display_path(&path)))
+    }
+
+    fn record_visit(&mut self, branch: String) {
+        self.history.retain(|entry| entry != &branch);
+        self.history.push(branch);
+        if self.history.len() > NAVIGATION_HISTORY_LIMIT {
+            let excess = self.history.len() - NAVIGATION_HISTORY_LIMIT;
+            self.history.drain(..excess);
+        }
+    }
+
+    /// Follows the remembered choice at every fork above `branch`, returning the
+    /// leaf it ends at or `None` if some fork has no (valid) remembered choice.
+    fn remembered_top(&self, metadata: &StackMetadata, branch: &str) -> Option<String> {
+        let mut current = branch.to_string();
+        loop {
+            let children = metadata.get_children(&current);
+            current = match children.len() {
+                0 => return Some(current),
+                1 => children[0].clone(),
+                _ => {
+                    let choice = self.fork_choices.get(&current)?;
+                    if !children.contains(choice) {
+                        return None;
+                    }
+                    choice.clone()
+                }
+            };
+        }
+    }
+
+    /// Records the child taken at each fork on the path from `from` up to `to`.
+    fn remember_path(&mut self, metadata: &StackMetadata, from: &str, to: &str) {
+        let mut current = to.to_string();
+        while current != from {
+            let Some(parent) = metadata.get_parent(&current) else {
+                return;
+            };
+            if metadata.get_children(&parent).len() > 1 {
+                self.fork_choices.insert(parent.clone(), current.clone());
+            }
+            current = parent;
+        }
+    }
+}
+
 fn collect_branch_sequence(metadata: &StackMetadata, start_branch: &str) -> Vec<String> {
     fn dfs(metadata: &StackMetadata, branch: &str, acc: &mut Vec<String>) {
         acc.push(branch.to_string());
@@ -1029,7 +4989,7 @@ fn execute_operation(
 
     state.save(repo_root)?;
     process_pending_operation(repo, repo_root, metadata, &mut state)?;
-    finalize_operation(repo_root, &state)
+    finalize_operation(repo, repo_root, metadata, &state)
 }
 
 fn continue_operation(
@@ -1051,17 +5011,21 @@ fn continue_operation(
     }
 
     if state.current_index >= state.branches.len() {
-        return finalize_operation(repo_root, &state);
+        return finalize_operation(repo, repo_root, metadata, &state);
     }
 
-    run_git_checked(repo_root, &["rebase", "--continue"])?;
-    state.current_index += 1;
-    state.save(repo_root)?;
+    // Only conflicts the in-memory rebase could not handle leave a git rebase behind
+    if rebase_in_progress(repo) {
+        run_git_checked(repo_root, &["rebase", "--continue"])?;
+        state.current_index += 1;
+        state.save(repo_root)?;
+    }
     process_pending_operation(repo, repo_root, metadata, &mut state)?;
-    finalize_operation(repo_root, &state)
+    finalize_operation(repo, repo_root, metadata, &state)
 }
 
 fn abort_operation(repo_root: &Path, kind: OperationKind) -> Result<()> {
+    let repo = Repository::open(repo_root).context("failed to open repository")?;
     let state = PendingOperation::load(repo_root)?
         .ok_or_else(|| anyhow!("No {} operation is currently in progress.", kind.name()))?;
 
@@ -1073,21 +5037,63 @@ fn abort_operation(repo_root: &Path, kind: OperationKind) -> Result<()> {
         );
     }
 
-    run_git_checked(repo_root, &["rebase", "--abort"])?;
+    if rebase_in_progress(&repo) {
+        run_git_checked(repo_root, &["rebase", "--abort"])?;
+    }
+    if !state.reparented.is_empty() {
+        let mut metadata = StackMetadata::load(repo_root)?;
+        for (branch, parent) in &state.reparented {
+            // A merged branch deleted in the meantime cannot take its children back
+            if metadata.branches.contains_key(parent) {
+                metadata.update_parent(branch, Some(parent.clone()));
+            }
+        }
+        metadata.save(repo_root)?;
+    }
     PendingOperation::clear(repo_root)?;
+    if state.autostash {
+        checkout_git_branch(repo_root, &state.original_branch)?;
+        run_git_checked(repo_root, &["stash", "pop"])?;
+    }
     println!("Aborted {} operation.", kind.name());
     Ok(())
 }
 
-fn finalize_operation(repo_root: &Path, state: &PendingOperation) -> Result<()> {
+fn finalize_operation(
+    repo: &Repository,
+    repo_root: &Path,
+    metadata: &StackMetadata,
+    state: &PendingOperation,
+) -> Result<()> {
     PendingOperation::clear(repo_root)?;
-    checkout_git_branch(repo_root, &state.original_branch)?;
-    println!(
-        "{} {} branch(es): {}",
-        state.kind.past_tense(),
-        state.branches.len(),
-        state.branches.join(" -> ")
-    );
+    // Only a conflict resolved in the working tree leaves another branch checked out
+    let on_original = repo.head().ok().is_some_and(|head| {
+        head.is_branch() && head.shorthand() == Some(state.original_branch.as_str())
+    });
+    if !on_original {
+        checkout_git_branch(repo_root, &state.original_branch)?;
+    }
+    if state.autostash {
+        run_git_checked(repo_root, &["stash", "pop"])?;
+    }
+    record_change_ids(repo, repo_root, metadata)?;
+    let rebased: Vec<&str> = state
+        .branches
+        .iter()
+        .filter(|branch| !state.up_to_date.contains(branch))
+        .map(String::as_str)
+        .collect();
+    if !rebased.is_empty() || state.up_to_date.is_empty() {
+        println!(
+            "{} {} branch(es): {}",
+            state.kind.past_tense(),
+            rebased.len(),
+            rebased.join(" -> ")
+        );
+    }
+    if !state.up_to_date.is_empty() {
+        println!("Already up to date: {}", state.up_to_date.join(", "));
+    }
     Ok(())
 }
 
@@ -1097,6 +5103,12 @@ fn process_pending_operation(
     metadata: &StackMetadata,
     state: &mut PendingOperation,
 ) -> Result<()> {
+    let mut fetched_parents: Vec<String> = Vec::new();
+    let committer = repo.signature().context(
+        "failed to get git signature. Ensure git user.name and user.email are configured.",
+    )?;
+    let signer = CommitSigner::resolve(repo, None, None)?;
+
     while state.current_index < state.branches.len() {
         let branch = state.branches[state.current_index].clone();
 
@@ -1104,14 +5116,61 @@ fn process_pending_operation(
             bail!("Branch '{}' no longer exists", branch);
         }
 
+        if metadata.is_frozen(&branch) {
+            println!("Skipping frozen branch '{}'", branch);
+            state.current_index += 1;
+            state.save(repo_root)?;
+            continue;
+        }
+
         let parent = metadata
             .get_parent(&branch)
             .ok_or_else(|| anyhow!("Branch '{}' has no recorded parent", branch))?;
 
-        checkout_git_branch(repo_root, &branch)?;
+        if !fetched_parents.contains(&parent)
+            && let Some(remote_branch) = split_remote_branch(repo, &parent)
+        {
+            println!("Fetching '{}'", parent);
+            fetch_remote_branch(repo_root, &remote_branch)?;
+            fetched_parents.push(parent.clone());
+        }
+
+        let tip = resolve_branch_commit(repo, &branch)?.id();
+        let onto = resolve_branch_commit(repo, &parent)?.id();
+        if is_up_to_date(repo, tip, onto)? {
+            println!(
+                "Skipping '{}': already up to date with '{}'",
+                branch, parent
+            );
+            state.up_to_date.push(branch);
+            state.current_index += 1;
+            state.save(repo_root)?;
+            continue;
+        }
+
         println!("Rebasing '{}' onto '{}'", branch, parent);
+        let base = state.base_for(&branch, onto)?;
+        if let RebaseOutcome::Rebased { tip: new_tip, .. } =
+            rebase_in_memory(repo, tip, base, onto, &committer, signer.as_ref())?
+        {
+            if new_tip != tip {
+                let reflog = format!("{}: rebase onto {}", state.kind.command_name(), parent);
+                move_branch(repo, &branch, new_tip, &reflog)?;
+            }
+            state.current_index += 1;
+            state.save(repo_root)?;
+            continue;
+        }
 
-        let output = run_git_command(repo_root, &["rebase", parent.as_str()])?;
+        // Conflicts need a rebase in the working tree for the user to resolve
+        checkout_git_branch(repo_root, &branch)?;
+        let output = match state.onto_bases.get(&branch) {
+            Some(base) => run_git_command(
+                repo_root,
+                &["rebase", "--onto", parent.as_str(), base.as_str()],
+            )?,
+            None => run_git_command(repo_root, &["rebase", parent.as_str()])?,
+        };
         if !output.status.success() {
             return Err(build_rebase_failure_message(&branch, &parent, &state.kind, &output));
         }
@@ -1123,6 +5182,249 @@ fn process_pending_operation(
     Ok(())
 }
 
+enum RebaseOutcome {
+    /// The new tip, with the number of commits rewritten and dropped as empty
+    Rebased {
+        tip: git2::Oid,
+        replayed: usize,
+        dropped: usize,
+    },
+    /// `commit` does not apply cleanly on top of `onto`
+    Conflict { commit: git2::Oid, onto: git2::Oid },
+    /// Merge commits are left to git's own rebase
+    Merge(git2::Oid),
+}
+
+/// Whether the branch at `tip` already sits on `onto`, leaving nothing to rebase.
+fn is_up_to_date(repo: &Repository, tip: git2::Oid, onto: git2::Oid) -> Result<bool> {
+    Ok(tip == onto
+        || repo
+            .graph_descendant_of(tip, onto)
+            .context("failed to compare branch history")?)
+}
+
+/// Rebases the commits after `base` up to `tip` onto `onto` without touching
+/// the working tree, dropping commits whose changes are already there like
+/// `git rebase` does. Stops at the first commit that conflicts or is a merge,
+/// which only git's own rebase can handle.
+fn rebase_in_memory(
+    repo: &Repository,
+    tip: git2::Oid,
+    base: git2::Oid,
+    onto: git2::Oid,
+    committer: &git2::Signature,
+    signer: Option<&CommitSigner>,
+) -> Result<RebaseOutcome> {
+    let mut walk = repo.revwalk().context("failed to walk history")?;
+    walk.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+        .context("failed to walk history")?;
+    walk.push(tip).context("failed to walk history")?;
+    walk.hide(base).context("failed to walk history")?;
+
+    let mut new_tip = onto;
+    let mut replayed = 0;
+    let mut dropped = 0;
+    for oid in walk {
+        let commit = repo
+            .find_commit(oid.context("failed to walk history")?)
+            .context("failed to find commit")?;
+        if commit.parent_count() != 1 {
+            return Ok(RebaseOutcome::Merge(commit.id()));
+        }
+        let parent = commit
+            .parent_id(0)
+            .context("failed to find parent commit")?;
+        if new_tip == parent {
+            new_tip = commit.id();
+            continue;
+        }
+
+        let Some(tree) = replay_tree(repo, &commit, new_tip, commit.tree_id())? else {
+            return Ok(RebaseOutcome::Conflict {
+                commit: commit.id(),
+                onto: new_tip,
+            });
+        };
+        let was_empty = commit.tree_id()
+            == repo
+                .find_commit(parent)
+                .context("failed to find parent commit")?
+                .tree_id();
+        let onto_tree = repo
+            .find_commit(new_tip)
+            .context("failed to find commit")?
+            .tree_id();
+        if tree == onto_tree && !was_empty {
+            dropped += 1;
+            continue;
+        }
+        new_tip = recommit(repo, &commit, new_tip, tree, committer, signer)?;
+        replayed += 1;
+    }
+    Ok(RebaseOutcome::Rebased {
+        tip: new_tip,
+        replayed,
+        dropped,
+    })
+}
+
+/// The paths that conflict when replaying `commit` on top of `onto`.
+fn conflicting_paths(repo: &Repository, commit: git2::Oid, onto: git2::Oid) -> Result<Vec<String>> {
+    let commit = repo.find_commit(commit).context("failed to find commit")?;
+    let ancestor = commit
+        .parent(0)
+        .and_then(|parent| parent.tree())
+        .context("failed to find parent commit")?;
+    let ours = repo
+        .find_commit(onto)
+        .and_then(|onto| onto.tree())
+        .context("failed to find tree")?;
+    let theirs = commit.tree().context("failed to find tree")?;
+    let index = repo
+        .merge_trees(&ancestor, &ours, &theirs, None)
+        .context("failed to merge trees")?;
+
+    let mut paths = Vec::new();
+    for conflict in index.conflicts().context("failed to read conflicts")? {
+        let conflict = conflict.context("failed to read conflicts")?;
+        if let Some(entry) = conflict.our.or(conflict.their).or(conflict.ancestor) {
+            paths.push(String::from_utf8_lossy(&entry.path).into_owned());
+        }
+    }
+    paths.sort();
+    paths.dedup();
+    Ok(paths)
+}
+
+/// Replays `state` the way `process_pending_operation` would and reports each
+/// branch's outcome. New objects only live in memory, and no refs or files change.
+fn simulate_operation(
+    repo_root: &Path,
+    metadata: &StackMetadata,
+    state: &PendingOperation,
+) -> Result<()> {
+    if state.branches.is_empty() {
+        println!("Nothing to {}.", state.kind.name());
+        return Ok(());
+    }
+
+    // A separate handle, so that the rewritten commits are never written to disk
+    let repo = Repository::open(repo_root).context("failed to open repository")?;
+    repo.odb()
+        .and_then(|odb| odb.add_new_mempack_backend(1000).map(|_| ()))
+        .context("failed to set up an in-memory object database")?;
+    let committer = repo.signature().context(
+        "failed to get git signature. Ensure git user.name and user.email are configured.",
+    )?;
+
+    println!(
+        "Dry run: {} {} branch(es)",
+        state.kind.name(),
+        state.branches.len()
+    );
+    let mut new_tips: HashMap<String, git2::Oid> = HashMap::new();
+    let mut blocked: Vec<String> = Vec::new();
+    for branch in &state.branches {
+        let parent = metadata
+            .get_parent(branch)
+            .ok_or_else(|| anyhow!("Branch '{}' has no recorded parent", branch))?;
+        let label = format!("  {} onto {}", branch, parent);
+        if metadata.is_frozen(branch) {
+            println!("{}: frozen, skipped", label);
+            continue;
+        }
+        if blocked.contains(&parent) {
+            println!("{}: unknown until '{}' is rebased", label, parent);
+            blocked.push(branch.clone());
+            continue;
+        }
+
+        let tip = resolve_branch_commit(&repo, branch)?.id();
+        let onto = match new_tips.get(&parent) {
+            Some(onto) => *onto,
+            None => resolve_branch_commit(&repo, &parent)?.id(),
+        };
+        if is_up_to_date(&repo, tip, onto)? {
+            println!("{}: up to date", label);
+            continue;
+        }
+        let base = state.base_for(branch, onto)?;
+        match rebase_in_memory(&repo, tip, base, onto, &committer, None)? {
+            RebaseOutcome::Rebased {
+                tip: new_tip,
+                replayed,
+                dropped,
+            } => {
+                let mut outcome = format!("{}: clean, {} commit(s) replayed", label, replayed);
+                if dropped > 0 {
+                    outcome.push_str(&format!(", {} already in '{}'", dropped, parent));
+                }
+                println!("{}", outcome);
+                new_tips.insert(branch.clone(), new_tip);
+            }
+            RebaseOutcome::Conflict { commit, onto } => {
+                let paths = conflicting_paths(&repo, commit, onto)?;
+                let commit = repo.find_commit(commit).context("failed to find commit")?;
+                println!(
+                    "{}: conflicts in {} while replaying {}",
+                    label,
+                    paths.join(", "),
+                    describe_commit(&commit)
+                );
+                blocked.push(branch.clone());
+            }
+            RebaseOutcome::Merge(commit) => {
+                let commit = repo.find_commit(commit).context("failed to find commit")?;
+                println!(
+                    "{}: contains merge commit {}, which needs `git rebase`",
+                    label,
+                    describe_commit(&commit)
+                );
+                blocked.push(branch.clone());
+            }
+        }
+    }
+    println!("No branches or files were changed.");
+    Ok(())
+}
+
+/// Points `branch` at `tip`. When the branch is checked out, the working tree
+/// and index follow, keeping unrelated local changes.
+fn move_branch(repo: &Repository, branch: &str, tip: git2::Oid, reflog: &str) -> Result<()> {
+    let refname = format!("refs/heads/{}", branch);
+    let checked_out = repo
+        .head()
+        .ok()
+        .is_some_and(|head| head.name() == Some(refname.as_str()));
+    if checked_out {
+        let tree = repo
+            .find_commit(tip)
+            .and_then(|commit| commit.tree())
+            .context("failed to find tree")?;
+        let mut builder = git2::build::CheckoutBuilder::new();
+        builder.safe();
+        repo.checkout_tree(tree.as_object(), Some(&mut builder))
+            .with_context(|| {
+                format!(
+                    "local changes on '{}' conflict with the rebased branch; commit or stash them, then continue",
+                    branch
+                )
+            })?;
+    }
+    repo.reference(&refname, tip, true, reflog)
+        .with_context(|| format!("failed to update branch '{}'", branch))?;
+    Ok(())
+}
+
+fn rebase_in_progress(repo: &Repository) -> bool {
+    matches!(
+        repo.state(),
+        git2::RepositoryState::Rebase
+            | git2::RepositoryState::RebaseInteractive
+            | git2::RepositoryState::RebaseMerge
+    )
+}
+
 fn build_rebase_failure_message(
     branch: &str,
     parent: &str,
@@ -1198,57 +5500,161 @@ fn format_git_error(args: &[&str], output: &std::process::Output) -> anyhow::Err
     anyhow!(message)
 }
 
-#[derive(Serialize)]
-struct PancakeConfig<'a> {
-    repository: RepositoryConfig<'a>,
-    pr: PrConfig<'a>,
-    stack: StackConfig<'a>,
+#[derive(Serialize, Deserialize)]
+struct PancakeConfig {
+    repository: RepositoryConfig,
+    #[serde(default)]
+    pr: PrConfig,
+    #[serde(default)]
+    stack: StackConfig,
+    #[serde(default)]
     github: GithubConfig,
+    #[serde(default)]
+    branch: BranchConfig,
+    #[serde(default)]
+    commit: CommitConfig,
+    #[serde(default)]
+    sync: SyncConfig,
 }
 
-impl<'a> PancakeConfig<'a> {
-    fn new(main_branch: &'a str, remote: &'a str) -> Self {
+impl PancakeConfig {
+    fn new(main_branch: &str, remote: &str) -> Self {
         Self {
             repository: RepositoryConfig {
-                main_branch,
-                remote,
-            },
-            pr: PrConfig {
-                auto_submit: false,
-                draft_by_default: false,
-                template: ".github/pull_request_template.md",
-            },
-            stack: StackConfig {
-                max_depth: 10,
-                prefix: "",
+                main_branch: main_branch.to_string(),
+                remote: remote.to_string(),
             },
-            github: GithubConfig { api_token: "" },
+            pr: PrConfig::default(),
+            stack: StackConfig::default(),
+            github: GithubConfig::default(),
+            branch: BranchConfig::default(),
+            commit: CommitConfig::default(),
+            sync: SyncConfig::default(),
         }
     }
+
+    fn path(repo_root: &Path) -> PathBuf {
+        repo_root.join(".pancake/config")
+    }
+
+    fn load(repo_root: &Path) -> Result<Self> {
+        let path = Self::path(repo_root);
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", display_path(&path)))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))
+    }
 }
 
-#[derive(Serialize)]
-struct RepositoryConfig<'a> {
-    main_branch: &'a str,
-    remote: &'a str,
+#[derive(Serialize, Deserialize)]
+struct RepositoryConfig {
+    main_branch: String,
+    remote: String,
 }
 
-#[derive(Serialize)]
-struct PrConfig<'a> {
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct PrConfig {
     auto_submit: bool,
     draft_by_default: bool,
-    template: &'a str,
+    template: String,
+}
+
+impl Default for PrConfig {
+    fn default() -> Self {
+        Self {
+            auto_submit: false,
+            draft_by_default: false,
+            template: ".github/pull_request_template.md".to_string(),
+        }
+    }
 }
 
-#[derive(Serialize)]
-struct StackConfig<'a> {
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct StackConfig {
     max_depth: u32,
-    prefix: &'a str,
+    prefix: String,
+}
+
+impl Default for StackConfig {
+    fn default() -> Self {
+        Self {
+            max_depth: 10,
+            prefix: String::new(),
+        }
+    }
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
 struct GithubConfig {
-    api_token: &'static str,
+    api_token: String,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+struct BranchConfig {
+    delete_remote: bool,
+    name_template: String,
+    name_date_format: String,
+    name_max_length: usize,
+}
+
+impl Default for BranchConfig {
+    fn default() -> Self {
+        Self {
+            delete_remote: false,
+            name_template: "{prefix}{slug}".to_string(),
+            name_date_format: "%m-%d".to_string(),
+            name_max_length: 50,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct CommitConfig {
+    change_id: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct SyncConfig {
+    fetch: bool,
+}
+
+/// User-wide settings from `~/.config/pancake/config.toml`.
+#[derive(Deserialize, Default)]
+struct GlobalConfig {
+    #[serde(default)]
+    defaults: DefaultsConfig,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct DefaultsConfig {
+    editor: Option<String>,
+}
+
+impl GlobalConfig {
+    fn path() -> Option<PathBuf> {
+        let config_home = std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|value| !value.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config_home.join("pancake/config.toml"))
+    }
+
+    fn load() -> Result<Self> {
+        let Some(path) = Self::path().filter(|path| path.exists()) else {
+            return Ok(Self::default());
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", display_path(&path)))?;
+        toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", display_path(&path)))
+    }
 }
 
 // Stack metadata structures
@@ -1286,6 +5692,7 @@ impl StackMetadata {
             BranchMetadata {
                 parent,
                 created_at: chrono::Utc::now().to_rfc3339(),
+                frozen: false,
             },
         );
     }
@@ -1319,19 +5726,35 @@ impl StackMetadata {
             .and_then(|m| m.parent.clone())
     }
 
-    fn find_stack_top(&self, branch_name: &str) -> String {
-        let mut current = branch_name.to_string();
-        loop {
-            let children = self.get_children(&current);
+    fn is_frozen(&self, branch_name: &str) -> bool {
+        self.branches
+            .get(branch_name)
+            .is_some_and(|m| m.frozen)
+    }
+
+    /// Returns every leaf reachable from `branch_name` with its distance from
+    /// it, in depth-first order with children sorted by name.
+    fn find_stack_leaves(&self, branch_name: &str) -> Vec<(String, usize)> {
+        fn walk(
+            metadata: &StackMetadata,
+            branch: &str,
+            depth: usize,
+            acc: &mut Vec<(String, usize)>,
+        ) {
+            let mut children = metadata.get_children(branch);
             if children.is_empty() {
-                return current;
+                acc.push((branch.to_string(), depth));
+                return;
             }
-            // If there are multiple children, we've reached the top for this path
-            if children.len() > 1 {
-                return current;
+            children.sort();
+            for child in children {
+                walk(metadata, &child, depth + 1, acc);
             }
-            current = children[0].clone();
         }
+
+        let mut leaves = Vec::new();
+        walk(self, branch_name, 0, &mut leaves);
+        leaves
     }
 
     fn find_stack_bottom(&self, branch_name: &str) -> String {
@@ -1353,11 +5776,17 @@ impl StackMetadata {
 struct BranchMetadata {
     parent: Option<String>,
     created_at: String,
+    #[serde(default)]
+    frozen: bool,
 }
 
 #[derive(Debug)]
 enum StackRoot {
-    ExternalParent { name: String, children: Vec<BranchNode> },
+    ExternalParent {
+        name: String,
+        remote: bool,
+        children: Vec<BranchNode>,
+    },
     Standalone { node: BranchNode },
 }
 
@@ -1367,7 +5796,7 @@ struct BranchNode {
     children: Vec<BranchNode>,
 }
 
-fn build_stack_forest(metadata: &StackMetadata) -> Vec<StackRoot> {
+fn build_stack_forest(repo: &Repository, metadata: &StackMetadata) -> Vec<StackRoot> {
     let mut children_map: HashMap<String, Vec<String>> = HashMap::new();
     let mut external_roots: HashMap<String, Vec<String>> = HashMap::new();
     let mut standalone_roots: Vec<String> = Vec::new();
@@ -1411,7 +5840,12 @@ fn build_stack_forest(metadata: &StackMetadata) -> Vec<StackRoot> {
             .into_iter()
             .map(|child| build_branch_node(&child, &children_map))
             .collect();
-        roots.push(StackRoot::ExternalParent { name, children });
+        let remote = split_remote_branch(repo, &name).is_some();
+        roots.push(StackRoot::ExternalParent {
+            name,
+            remote,
+            children,
+        });
     }
 
     for branch_name in standalone_roots {
@@ -1455,8 +5889,16 @@ fn render_full_view(roots: &[StackRoot]) {
         let color = colors[idx % colors.len()];
 
         match root {
-            StackRoot::ExternalParent { name, children } => {
-                println!("{}", name.color(color).bold());
+            StackRoot::ExternalParent {
+                name,
+                remote,
+                children,
+            } => {
+                if *remote {
+                    println!("{} {}", name.color(color).bold(), "(remote)".dimmed());
+                } else {
+                    println!("{}", name.color(color).bold());
+                }
                 render_children(children, color);
             }
             StackRoot::Standalone { node } => {
@@ -1514,7 +5956,7 @@ fn render_short_view(roots: &[StackRoot]) {
         let mut lines = Vec::new();
 
         match root {
-            StackRoot::ExternalParent { name, children } => {
+            StackRoot::ExternalParent { name, children, .. } => {
                 for child in children {
                     collect_paths(child, vec![name.clone()], &mut lines);
                 }
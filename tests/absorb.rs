@@ -0,0 +1,316 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn absorb_requires_staged_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    pk_cmd()
+        .args(["absorb"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("No staged changes to absorb"));
+}
+
+#[test]
+fn absorb_folds_hunks_into_owning_commits() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\nthree\n").expect("write file");
+    fs::write(repo.path().join("top.txt"), "four\nfive\nSIX\n").expect("write file");
+
+    pk_cmd()
+        .args(["absorb", "-a"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Absorbed base.txt:2 into"))
+        .stdout(contains("base commit on 'feature/base'"))
+        .stdout(contains("Absorbed top.txt:3 into"))
+        .stdout(contains("top commit on 'feature/top'"));
+
+    assert_eq!(
+        show_file(repo.path(), "feature/base", "base.txt"),
+        "one\nTWO\nthree\n"
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "top.txt"),
+        "four\nfive\nSIX\n"
+    );
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 1);
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 1);
+    assert_eq!(
+        git_output(
+            repo.path(),
+            &["status", "--porcelain", "--untracked-files=no"]
+        ),
+        ""
+    );
+    assert_eq!(current_branch(repo.path()), "feature/top");
+}
+
+#[test]
+fn absorb_places_added_lines_next_to_their_neighbours() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(
+        repo.path().join("base.txt"),
+        "one\ntwo\ntwo and a half\nthree\n",
+    )
+    .expect("write file");
+
+    pk_cmd()
+        .args(["absorb", "-a"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Absorbed base.txt:2 into"));
+
+    assert_eq!(
+        show_file(repo.path(), "feature/base", "base.txt"),
+        "one\ntwo\ntwo and a half\nthree\n"
+    );
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 1);
+}
+
+#[test]
+fn absorb_reports_hunks_it_cannot_place() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("README.md"), "# Changed readme").expect("write file");
+    fs::write(repo.path().join("new.txt"), "brand new").expect("write file");
+    fs::write(repo.path().join("top.txt"), "four\nFIVE\nsix\n").expect("write file");
+    run_git(repo.path(), &["add", "README.md", "new.txt", "top.txt"]);
+
+    pk_cmd()
+        .args(["absorb"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Could not absorb README.md:1: last changed outside the stack",
+        ))
+        .stdout(contains(
+            "Could not absorb new.txt: new files have no commit to absorb into",
+        ))
+        .stdout(contains("Absorbed top.txt:2 into"))
+        .stdout(contains("Left 2 hunk(s) staged"));
+
+    assert_eq!(
+        git_output(repo.path(), &["diff", "--cached", "--name-only"]),
+        "README.md\nnew.txt\n"
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "top.txt"),
+        "four\nFIVE\nsix\n"
+    );
+}
+
+#[test]
+fn absorb_fixup_creates_fixup_commits() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\nthree\n").expect("write file");
+
+    pk_cmd()
+        .args(["absorb", "-a", "--fixup"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 2);
+    assert_eq!(
+        git_output(repo.path(), &["log", "-1", "--pretty=%s", "feature/base"]),
+        "fixup! base commit\n"
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/base~1", "base.txt"),
+        "one\ntwo\nthree\n"
+    );
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "feature/base"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    assert_eq!(
+        git_output(
+            repo.path(),
+            &["status", "--porcelain", "--untracked-files=no"]
+        ),
+        ""
+    );
+}
+
+#[test]
+fn absorb_restacks_child_branches() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/child"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "child.txt", "child\n", "child commit");
+    run_git(repo.path(), &["checkout", "feature/top"]);
+
+    fs::write(repo.path().join("base.txt"), "ONE\ntwo\nthree\n").expect("write file");
+    // Unstaged edits survive the restack of the child branch
+    fs::write(repo.path().join("top.txt"), "four\nfive\nsix\nseven\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["absorb"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Rebasing 'feature/child' onto 'feature/top'"))
+        .stdout(contains("Restacked 1 branch(es)"));
+
+    assert_eq!(current_branch(repo.path()), "feature/top");
+    assert_eq!(
+        merge_base(repo.path(), "feature/child", "feature/top"),
+        rev_parse(repo.path(), "feature/top")
+    );
+    assert_eq!(commit_count(repo.path(), "feature/top..feature/child"), 1);
+    assert_eq!(
+        show_file(repo.path(), "feature/child", "base.txt"),
+        "ONE\ntwo\nthree\n"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("top.txt")).expect("read file"),
+        "four\nfive\nsix\nseven\n"
+    );
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Builds `main -> feature/base -> feature/top`, each branch adding one
+/// three-line file, and leaves `feature/top` checked out.
+fn create_stack(repo: &TestRepo) {
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "base.txt", "one\ntwo\nthree\n", "base commit");
+
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "top.txt", "four\nfive\nsix\n", "top commit");
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    git_output(dir, &["merge-base", left, right])
+        .trim()
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn commit_count(dir: &Path, range: &str) -> usize {
+    git_output(dir, &["rev-list", "--count", range])
+        .trim()
+        .parse()
+        .expect("parse commit count")
+}
+
+fn show_file(dir: &Path, rev: &str, path: &str) -> String {
+    git_output(dir, &["show", &format!("{rev}:{path}")])
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
@@ -0,0 +1,305 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn amend_requires_init() {
+    let repo = TestRepo::new("main");
+
+    pk_cmd()
+        .args(["amend"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Pancake is not initialized"));
+}
+
+#[test]
+fn amend_keeps_message_and_restacks_descendants() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    run_git(repo.path(), &["checkout", "feature/base"]);
+    fs::write(repo.path().join("base.txt"), "base amended").expect("write file");
+
+    pk_cmd()
+        .args(["amend", "-a"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Amended commit on branch 'feature/base'"))
+        .stdout(contains("Rebasing 'feature/top' onto 'feature/base'"))
+        .stdout(contains("Restacked 1 branch(es)"));
+
+    assert_eq!(current_branch(repo.path()), "feature/base");
+    assert_eq!(commit_subject(repo.path(), "feature/base"), "base commit");
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "feature/base"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    // Only the top branch's own commit is replayed, not the pre-amend base commit
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 1);
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "base.txt"),
+        "base amended"
+    );
+}
+
+#[test]
+fn amend_replaces_message() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    pk_cmd()
+        .args(["amend", "-m", "better top commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Amended commit on branch 'feature/top'"));
+
+    assert_eq!(commit_subject(repo.path(), "HEAD"), "better top commit");
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 1);
+}
+
+#[test]
+fn amend_warns_when_nothing_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    pk_cmd()
+        .args(["amend"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stderr(contains("Warning: the amend changes neither the tree nor the message"));
+}
+
+#[test]
+fn amend_edit_seeds_editor_with_current_message() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    let tools = TempDir::new().expect("temp dir");
+    let seen = tools.path().join("seen.txt");
+    let script = tools.path().join("editor.sh");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\ncp \"$1\" '{}'\nprintf 'edited top' > \"$1\"\n",
+            seen.display()
+        ),
+    )
+    .expect("write editor script");
+
+    pk_cmd()
+        .args(["amend", "--edit"])
+        .current_dir(repo.path())
+        .env("GIT_EDITOR", format!("sh {}", script.display()))
+        .env("XDG_CONFIG_HOME", repo.path())
+        .assert()
+        .success();
+
+    let template = fs::read_to_string(seen).expect("read template");
+    assert!(template.starts_with("top commit"));
+    assert_eq!(commit_subject(repo.path(), "HEAD"), "edited top");
+}
+
+#[test]
+fn amend_continue_after_conflict() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "shared.txt", "base\n", "base commit");
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "shared.txt", "top\n", "top commit");
+
+    run_git(repo.path(), &["checkout", "feature/base"]);
+    fs::write(repo.path().join("shared.txt"), "base amended\n").expect("write file");
+
+    pk_cmd()
+        .args(["amend", "-a"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("pk amend --continue"));
+
+    fs::write(repo.path().join("shared.txt"), "resolved\n").expect("write file");
+    run_git(repo.path(), &["add", "shared.txt"]);
+
+    pk_cmd()
+        .args(["amend", "--continue"])
+        .current_dir(repo.path())
+        .env("GIT_EDITOR", "true")
+        .assert()
+        .success()
+        .stdout(contains("Restacked 1 branch(es)"));
+
+    assert_eq!(current_branch(repo.path()), "feature/base");
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "feature/base"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "shared.txt"),
+        "resolved\n"
+    );
+}
+
+#[test]
+fn amend_refuses_frozen_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    pk_cmd()
+        .args(["branch", "freeze"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["amend", "-m", "rewritten"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Branch 'feature/top' is frozen"));
+
+    assert_eq!(commit_subject(repo.path(), "HEAD"), "top commit");
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Builds `main -> feature/base -> feature/top` with one commit per branch.
+fn create_stack(repo: &TestRepo) {
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "base.txt", "base", "base commit");
+
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "top.txt", "top", "top commit");
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    git_output(dir, &["merge-base", left, right])
+        .trim()
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn commit_subject(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["log", "-1", "--pretty=%s", rev])
+        .trim()
+        .to_string()
+}
+
+fn commit_count(dir: &Path, range: &str) -> usize {
+    git_output(dir, &["rev-list", "--count", range])
+        .trim()
+        .parse()
+        .expect("parse commit count")
+}
+
+fn show_file(dir: &Path, rev: &str, path: &str) -> String {
+    git_output(dir, &["show", &format!("{rev}:{path}")])
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
@@ -0,0 +1,294 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn commit_fixup_targets_commit_in_lower_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--fixup", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created commit on branch 'feature/top'"));
+
+    assert_eq!(commit_subject(repo.path(), "HEAD"), "fixup! base commit");
+}
+
+#[test]
+fn commit_fixup_rejects_commits_outside_the_stack() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--fixup", "main"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("is not part of the stack below 'feature/top'"));
+}
+
+#[test]
+fn autosquash_folds_fixups_into_lower_branches() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args(["commit", "--fixup", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["autosquash"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("fixup! base commit into"));
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 1);
+    assert_eq!(commit_subject(repo.path(), "feature/base"), "base commit");
+    assert_eq!(
+        show_file(repo.path(), "feature/base", "base.txt"),
+        "one\nTWO\n"
+    );
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 1);
+    assert_eq!(commit_subject(repo.path(), "feature/top"), "top commit");
+    assert_eq!(
+        git_output(
+            repo.path(),
+            &["status", "--porcelain", "--untracked-files=no"]
+        ),
+        ""
+    );
+}
+
+#[test]
+fn autosquash_appends_squash_messages() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args([
+            "commit",
+            "--squash",
+            "feature/base",
+            "-m",
+            "Shout the second line",
+        ])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["autosquash"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 1);
+    assert_eq!(
+        git_output(repo.path(), &["log", "-1", "--pretty=%B", "feature/base"]),
+        "base commit\n\nShout the second line\n\n"
+    );
+}
+
+#[test]
+fn autosquash_restacks_descendants() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    pk_cmd()
+        .args(["bc", "feature/child"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "child.txt", "child\n", "child commit");
+    run_git(repo.path(), &["checkout", "feature/top"]);
+
+    fs::write(repo.path().join("base.txt"), "one\nTWO\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args(["commit", "--fixup", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["autosquash"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Rebasing 'feature/child' onto 'feature/top'"))
+        .stdout(contains("Restacked 1 branch(es)"));
+
+    assert_eq!(current_branch(repo.path()), "feature/top");
+    assert_eq!(commit_count(repo.path(), "feature/top..feature/child"), 1);
+    assert_eq!(
+        merge_base(repo.path(), "feature/child", "feature/top"),
+        rev_parse(repo.path(), "feature/top")
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/child", "base.txt"),
+        "one\nTWO\n"
+    );
+}
+
+#[test]
+fn autosquash_without_fixups_does_nothing() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    let before = rev_parse(repo.path(), "HEAD");
+
+    pk_cmd()
+        .args(["autosquash"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("No fixup commits to fold."));
+
+    assert_eq!(rev_parse(repo.path(), "HEAD"), before);
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Builds `main -> feature/base -> feature/top`, each branch adding one
+/// two-line file, and leaves `feature/top` checked out.
+fn create_stack(repo: &TestRepo) {
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "base.txt", "one\ntwo\n", "base commit");
+
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "top.txt", "three\nfour\n", "top commit");
+}
+
+fn commit_subject(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["log", "-1", "--pretty=%s", rev])
+        .trim()
+        .to_string()
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    git_output(dir, &["merge-base", left, right])
+        .trim()
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn commit_count(dir: &Path, range: &str) -> usize {
+    git_output(dir, &["rev-list", "--count", range])
+        .trim()
+        .parse()
+        .expect("parse commit count")
+}
+
+fn show_file(dir: &Path, rev: &str, path: &str) -> String {
+    git_output(dir, &["show", &format!("{rev}:{path}")])
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
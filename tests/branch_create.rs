@@ -1,6 +1,6 @@
 use std::{fs, path::Path, process::Command as StdCommand};
 
-use predicates::str::contains;
+use predicates::{prelude::*, str::contains};
 use tempfile::TempDir;
 
 #[test]
@@ -192,6 +192,187 @@ fn bc_top_level_alias_works() {
     );
 }
 
+#[test]
+fn branch_create_with_message_commits_staged_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    fs::write(repo.path().join("feature.txt"), "feature").expect("write file");
+    run_git(repo.path(), &["add", "feature.txt"]);
+
+    pk_cmd()
+        .args(["bc", "feature/explicit", "-m", "Add feature file"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created branch 'feature/explicit' based on 'main'"))
+        .stdout(contains("Created commit on branch 'feature/explicit'"));
+
+    assert_eq!(current_branch(repo.path()), "feature/explicit");
+    assert_eq!(git_output(repo.path(), &["log", "-1", "--format=%s"]), "Add feature file");
+    assert_eq!(
+        git_output(repo.path(), &["show", "--name-only", "--format=", "HEAD"]),
+        "feature.txt"
+    );
+    assert_eq!(
+        git_output(repo.path(), &["rev-parse", "main"]),
+        git_output(repo.path(), &["rev-parse", "HEAD~1"])
+    );
+}
+
+#[test]
+fn branch_create_generates_name_from_message() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    fs::write(repo.path().join("README.md"), "# Changed").expect("write file");
+
+    pk_cmd()
+        .args(["bc", "-a", "-m", "Fix: the Login button!\n\nLonger body"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created branch 'fix-the-login-button' based on 'main'"));
+
+    assert_eq!(current_branch(repo.path()), "fix-the-login-button");
+    assert_eq!(git_output(repo.path(), &["status", "--porcelain", "README.md"]), "");
+
+    let metadata = read_metadata(&repo);
+    assert_eq!(
+        metadata["branches"]["fix-the-login-button"]["parent"].as_str(),
+        Some("main")
+    );
+}
+
+#[test]
+fn branch_create_name_template_applies_prefix_and_max_length() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    let config_path = repo.path().join(".pancake/config");
+    let config = fs::read_to_string(&config_path).expect("config should exist");
+    let config = config
+        .replace("prefix = \"\"", "prefix = \"jd/\"")
+        .replace("name_max_length = 50", "name_max_length = 16");
+    fs::write(&config_path, config).expect("write config");
+    fs::write(repo.path().join("cache.txt"), "cache").expect("write file");
+    run_git(repo.path(), &["add", "cache.txt"]);
+
+    pk_cmd()
+        .args(["bc", "-m", "Refactor the session cache"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created branch 'jd/refactor-the'"));
+
+    assert_eq!(current_branch(repo.path()), "jd/refactor-the");
+}
+
+#[test]
+fn branch_create_with_message_refuses_empty_commit() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("unstaged.txt"), "unstaged").expect("write file");
+
+    pk_cmd()
+        .args(["bc", "feature/empty", "-m", "Nothing staged"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Nothing to commit on branch 'feature/empty'"))
+        .stderr(contains("pk bc -a"));
+
+    assert_eq!(current_branch(repo.path()), "main");
+    assert!(!branch_exists(repo.path(), "feature/empty"));
+
+    pk_cmd()
+        .args(["bc", "feature/empty", "-m", "Empty on purpose", "--allow-empty"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created commit on branch 'feature/empty'"));
+
+    assert_eq!(git_output(repo.path(), &["log", "-1", "--format=%s"]), "Empty on purpose");
+    assert_eq!(
+        git_output(repo.path(), &["rev-parse", "HEAD^{tree}"]),
+        git_output(repo.path(), &["rev-parse", "main^{tree}"])
+    );
+}
+
+#[test]
+fn branch_create_rejects_invalid_date_format() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    let config_path = repo.path().join(".pancake/config");
+    let config = fs::read_to_string(&config_path).expect("config should exist");
+    let config = config
+        .replace("name_template = \"{prefix}{slug}\"", "name_template = \"{date}-{slug}\"")
+        .replace("name_date_format = \"%m-%d\"", "name_date_format = \"%Q\"");
+    fs::write(&config_path, config).expect("write config");
+
+    pk_cmd()
+        .args(["bc", "-m", "Dated work"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("invalid `branch.name_date_format` '%Q'"))
+        .stderr(contains("panicked").not());
+
+    assert_eq!(current_branch(repo.path()), "main");
+}
+
+#[test]
+fn branch_create_requires_name_or_message() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Branch name is required"));
+}
+
+#[test]
+fn branch_create_accepts_remote_tracking_base() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let remote = TempDir::new().expect("temp dir");
+    run_git(remote.path(), &["init", "--bare"]);
+    run_git(
+        repo.path(),
+        &["remote", "add", "origin", remote.path().to_str().unwrap()],
+    );
+    run_git(repo.path(), &["checkout", "-b", "teammate"]);
+    fs::write(repo.path().join("teammate.txt"), "teammate").expect("write file");
+    run_git(repo.path(), &["add", "teammate.txt"]);
+    run_git(repo.path(), &["commit", "-m", "teammate work"]);
+    run_git(repo.path(), &["push", "origin", "teammate"]);
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["branch", "-D", "teammate"]);
+
+    pk_cmd()
+        .args(["bc", "feature/on-teammate", "--base", "origin/teammate"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created branch 'feature/on-teammate' based on 'origin/teammate'"));
+
+    assert!(!branch_exists(repo.path(), "refs/heads/teammate"));
+    assert_eq!(
+        git_output(repo.path(), &["rev-parse", "HEAD"]),
+        git_output(repo.path(), &["rev-parse", "origin/teammate"])
+    );
+
+    let metadata = read_metadata(&repo);
+    assert_eq!(
+        metadata["branches"]["feature/on-teammate"]["parent"].as_str(),
+        Some("origin/teammate")
+    );
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -268,6 +449,16 @@ fn run_git(dir: &Path, args: &[&str]) {
     assert!(status.success(), "git {:?} failed", args);
 }
 
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
 fn pk_cmd() -> assert_cmd::Command {
     #[allow(deprecated)]
     {
@@ -223,6 +223,261 @@ fn bd_top_level_alias_works() {
     assert!(!branch_exists(repo.path(), "feature/bd-test"));
 }
 
+#[test]
+fn branch_delete_refuses_unmerged_commits_without_force() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/unmerged"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "unmerged work");
+
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["bd", "feature/unmerged"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("has 1 unmerged commit(s)"))
+        .stderr(contains("unmerged work"))
+        .stderr(contains("--force"));
+
+    assert!(branch_exists(repo.path(), "feature/unmerged"));
+    let metadata = read_metadata(&repo);
+    assert!(metadata["branches"].as_object().unwrap().contains_key("feature/unmerged"));
+}
+
+#[test]
+fn branch_delete_allows_branch_merged_into_trunk() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/merged"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "merged work");
+
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--ff-only", "feature/merged"]);
+
+    pk_cmd()
+        .args(["bd", "feature/merged"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Deleted branch 'feature/merged'"));
+
+    assert!(!branch_exists(repo.path(), "feature/merged"));
+}
+
+#[test]
+fn branch_delete_force_records_tip_for_restore() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/doomed"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "doomed work");
+    let tip = rev_parse(repo.path(), "feature/doomed");
+
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["bd", "feature/doomed", "--force"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Warning: deleting 1 unmerged commit(s)"))
+        .stdout(contains(format!("git branch feature/doomed {tip}")));
+
+    assert!(!branch_exists(repo.path(), "feature/doomed"));
+
+    let raw = fs::read_to_string(repo.path().join(".pancake/deleted_branches.json"))
+        .expect("deleted branch log should exist");
+    let log: serde_json::Value = serde_json::from_str(&raw).expect("valid json");
+    let entry = &log["branches"][0];
+    assert_eq!(entry["name"].as_str(), Some("feature/doomed"));
+    assert_eq!(entry["tip"].as_str(), Some(tip.as_str()));
+    assert_eq!(entry["parent"].as_str(), Some("main"));
+}
+
+#[test]
+fn branch_delete_remote_removes_pushed_branch() {
+    let repo = TestRepo::new("main");
+    let remote = TestRemote::new(&repo);
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/pushed"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "pushed work");
+    run_git(repo.path(), &["push", "-u", "origin", "feature/pushed"]);
+
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["bd", "feature/pushed", "--remote", "--force"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Deleted remote branch 'origin/feature/pushed'"));
+
+    assert!(!branch_exists(repo.path(), "feature/pushed"));
+    assert!(!branch_exists(remote.path(), "feature/pushed"));
+}
+
+#[test]
+fn branch_delete_remote_refuses_when_remote_moved() {
+    let repo = TestRepo::new("main");
+    let remote = TestRemote::new(&repo);
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/shared"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "my work");
+    run_git(repo.path(), &["push", "-u", "origin", "feature/shared"]);
+
+    // A collaborator pushes on top of the branch
+    let other = TempDir::new().expect("temp dir");
+    run_git(
+        other.path(),
+        &["clone", "-b", "feature/shared", remote.path().to_str().unwrap(), "."],
+    );
+    fs::write(other.path().join("theirs.txt"), "theirs").expect("write file");
+    run_git(other.path(), &["add", "theirs.txt"]);
+    run_git(other.path(), &["commit", "-m", "their work"]);
+    run_git(other.path(), &["push", "origin", "feature/shared"]);
+    let their_tip = rev_parse(other.path(), "HEAD");
+
+    // Our own work is merged, so only the collaborator's commit is at stake
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--ff-only", "feature/shared"]);
+
+    pk_cmd()
+        .args(["bd", "feature/shared", "--remote"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("has commits that are not on the local branch"));
+
+    assert!(branch_exists(repo.path(), "feature/shared"));
+    assert_eq!(rev_parse(remote.path(), "feature/shared"), their_tip);
+}
+
+#[test]
+fn branch_delete_remote_refuses_after_fetching_collaborator_commits() {
+    let repo = TestRepo::new("main");
+    let remote = TestRemote::new(&repo);
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/shared"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "work.txt", "work", "my work");
+    run_git(repo.path(), &["push", "-u", "origin", "feature/shared"]);
+
+    let other = TempDir::new().expect("temp dir");
+    run_git(
+        other.path(),
+        &["clone", "-b", "feature/shared", remote.path().to_str().unwrap(), "."],
+    );
+    fs::write(other.path().join("theirs.txt"), "theirs").expect("write file");
+    run_git(other.path(), &["add", "theirs.txt"]);
+    run_git(other.path(), &["commit", "-m", "their work"]);
+    run_git(other.path(), &["push", "origin", "feature/shared"]);
+    let their_tip = rev_parse(other.path(), "HEAD");
+
+    // Fetching moves the remote-tracking ref past the local branch tip
+    run_git(repo.path(), &["fetch", "origin"]);
+    assert_eq!(rev_parse(repo.path(), "origin/feature/shared"), their_tip);
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--ff-only", "feature/shared"]);
+
+    pk_cmd()
+        .args(["bd", "feature/shared", "--remote"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("has commits that are not on the local branch"));
+
+    assert!(branch_exists(repo.path(), "feature/shared"));
+    assert_eq!(rev_parse(remote.path(), "feature/shared"), their_tip);
+
+    pk_cmd()
+        .args(["bd", "feature/shared", "--remote", "--force"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Warning: deleting remote commits of 'origin/feature/shared'"))
+        .stdout(contains("Deleted remote branch 'origin/feature/shared'"));
+
+    assert!(!branch_exists(repo.path(), "feature/shared"));
+    assert!(!branch_exists(remote.path(), "feature/shared"));
+}
+
+#[test]
+fn branch_delete_remote_can_be_enabled_in_config() {
+    let repo = TestRepo::new("main");
+    let remote = TestRemote::new(&repo);
+    init_pk(&repo);
+
+    let config_path = repo.path().join(".pancake/config");
+    let config = fs::read_to_string(&config_path).expect("config should exist");
+    assert!(config.contains("delete_remote = false"));
+    fs::write(
+        &config_path,
+        config.replace("delete_remote = false", "delete_remote = true"),
+    )
+    .expect("write config");
+
+    pk_cmd()
+        .args(["bc", "feature/configured"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    run_git(repo.path(), &["push", "-u", "origin", "feature/configured"]);
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["bd", "feature/configured", "--no-remote"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    assert!(branch_exists(remote.path(), "feature/configured"));
+
+    pk_cmd()
+        .args(["bc", "feature/other"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    run_git(repo.path(), &["push", "origin", "feature/other"]);
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["bd", "feature/other"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Deleted remote branch 'origin/feature/other'"));
+    assert!(!branch_exists(remote.path(), "feature/other"));
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -245,6 +500,27 @@ impl TestRepo {
     }
 }
 
+struct TestRemote {
+    dir: TempDir,
+}
+
+impl TestRemote {
+    fn new(repo: &TestRepo) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init", "--bare"]);
+        run_git(
+            repo.path(),
+            &["remote", "add", "origin", dir.path().to_str().unwrap()],
+        );
+        run_git(repo.path(), &["push", "origin", "main"]);
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
 fn init_pk(repo: &TestRepo) {
     pk_cmd()
         .arg("init")
@@ -259,6 +535,22 @@ fn read_metadata(repo: &TestRepo) -> serde_json::Value {
     serde_json::from_str(&raw).expect("metadata should be valid json")
 }
 
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    let output = StdCommand::new("git")
+        .args(["rev-parse", rev])
+        .current_dir(dir)
+        .output()
+        .expect("git rev-parse");
+    assert!(output.status.success(), "rev-parse failed");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
 fn branch_exists(dir: &Path, branch: &str) -> bool {
     StdCommand::new("git")
         .args(["rev-parse", "--verify", branch])
@@ -0,0 +1,240 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn freeze_marks_branch_in_metadata() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/colleague"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["branch", "freeze"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Froze branch 'feature/colleague'"));
+
+    let metadata = read_metadata(&repo);
+    assert_eq!(metadata["branches"]["feature/colleague"]["frozen"].as_bool(), Some(true));
+
+    pk_cmd()
+        .args(["branch", "unfreeze", "feature/colleague"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Unfroze branch 'feature/colleague'"));
+
+    let metadata = read_metadata(&repo);
+    assert_eq!(metadata["branches"]["feature/colleague"]["frozen"].as_bool(), Some(false));
+}
+
+#[test]
+fn freeze_rejects_untracked_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["branch", "freeze", "main"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Branch 'main' is not tracked by Pancake"));
+}
+
+#[test]
+fn restack_skips_frozen_branch_but_rebases_children() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/colleague"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "colleague.txt", "colleague", "colleague commit");
+
+    pk_cmd()
+        .args(["bc", "feature/mine"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "mine.txt", "mine", "my commit");
+
+    pk_cmd()
+        .args(["branch", "freeze", "feature/colleague"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "README.md", "main updated", "main update");
+
+    // The colleague amends their branch; our child must follow it
+    run_git(repo.path(), &["checkout", "feature/colleague"]);
+    write_and_commit(&repo, "colleague.txt", "colleague v2", "colleague update");
+    let frozen_tip = rev_parse(repo.path(), "feature/colleague");
+
+    run_git(repo.path(), &["checkout", "feature/mine"]);
+
+    pk_cmd()
+        .args(["restack"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Skipping frozen branch 'feature/colleague'"))
+        .stdout(contains("Rebasing 'feature/mine' onto 'feature/colleague'"));
+
+    assert_eq!(rev_parse(repo.path(), "feature/colleague"), frozen_tip);
+    assert_eq!(
+        merge_base(repo.path(), "feature/mine", "feature/colleague"),
+        frozen_tip
+    );
+}
+
+#[test]
+fn commit_refuses_frozen_branch_without_force() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/colleague"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["branch", "freeze"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    fs::write(repo.path().join("file.txt"), "content").expect("write file");
+    run_git(repo.path(), &["add", "file.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-m", "touch frozen"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Branch 'feature/colleague' is frozen"));
+
+    pk_cmd()
+        .args(["commit", "--amend", "-m", "touch frozen"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("is frozen"));
+
+    pk_cmd()
+        .args(["commit", "-m", "touch frozen", "--force"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn read_metadata(repo: &TestRepo) -> serde_json::Value {
+    let metadata_path = repo.path().join(".pancake/stacks.json");
+    let raw = fs::read_to_string(metadata_path).expect("metadata should exist");
+    serde_json::from_str(&raw).expect("metadata should be valid json")
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    let output = StdCommand::new("git")
+        .args(["merge-base", left, right])
+        .current_dir(dir)
+        .output()
+        .expect("git merge-base");
+    assert!(output.status.success(), "merge-base failed");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    let output = StdCommand::new("git")
+        .args(["rev-parse", rev])
+        .current_dir(dir)
+        .output()
+        .expect("git rev-parse");
+    assert!(output.status.success(), "rev-parse failed");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn current_branch(dir: &Path) -> String {
+    let output = StdCommand::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .current_dir(dir)
+        .output()
+        .expect("git rev-parse");
+    assert!(output.status.success(), "failed to query current branch");
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
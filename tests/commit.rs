@@ -1,6 +1,6 @@
 use std::{fs, path::Path, process::Command as StdCommand};
 
-use predicates::str::contains;
+use predicates::{prelude::*, str::contains};
 use tempfile::TempDir;
 
 #[test]
@@ -19,13 +19,18 @@ fn commit_requires_init() {
 fn commit_requires_message() {
     let repo = TestRepo::new("main");
     init_pk(&repo);
+    fs::write(repo.path().join("test.txt"), "hello world").expect("write file");
+    run_git(repo.path(), &["add", "test.txt"]);
 
+    // An editor that leaves the template untouched yields an empty message
     pk_cmd()
         .args(["commit"])
         .current_dir(repo.path())
+        .env("GIT_EDITOR", "true")
+        .env("XDG_CONFIG_HOME", repo.path())
         .assert()
         .failure()
-        .stderr(contains("required"));
+        .stderr(contains("Aborting commit due to empty commit message"));
 }
 
 #[test]
@@ -179,17 +184,47 @@ fn commit_works_on_feature_branch() {
 }
 
 #[test]
-fn commit_allows_empty_commit() {
+fn commit_refuses_empty_commit() {
     let repo = TestRepo::new("main");
     init_pk(&repo);
 
     let initial_count = commit_count(repo.path());
 
-    // Create a commit without any changes (empty commit)
     pk_cmd()
         .args(["commit", "-m", "Empty commit"])
         .current_dir(repo.path())
         .assert()
+        .failure()
+        .stderr(contains("Nothing to commit on branch 'main': the working tree is clean"))
+        .stderr(contains("--allow-empty"));
+
+    // Unstaged changes are listed along with how to stage them
+    fs::write(repo.path().join("README.md"), "changed").expect("write file");
+    fs::write(repo.path().join("new.txt"), "new").expect("write file");
+    pk_cmd()
+        .args(["commit", "-m", "Empty commit"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("no changes are staged"))
+        .stderr(contains("modified:   README.md"))
+        .stderr(contains("untracked:  new.txt"))
+        .stderr(contains("pk commit -a"));
+
+    assert_eq!(commit_count(repo.path()), initial_count);
+}
+
+#[test]
+fn commit_allow_empty_creates_empty_commit() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    let initial_count = commit_count(repo.path());
+
+    pk_cmd()
+        .args(["commit", "--allow-empty", "-m", "Empty commit"])
+        .current_dir(repo.path())
+        .assert()
         .success()
         .stdout(contains("Created commit on branch 'main'"));
 
@@ -198,6 +233,27 @@ fn commit_allows_empty_commit() {
     assert_eq!(last_commit_message(repo.path()), "Empty commit");
 }
 
+#[test]
+fn commit_amend_warns_when_nothing_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["commit", "--amend", "-m", "init"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stderr(contains("Warning: the amend changes neither the tree nor the message"));
+
+    pk_cmd()
+        .args(["commit", "--amend", "-m", "reworded"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stderr(contains("Warning").not());
+    assert_eq!(last_commit_message(repo.path()), "reworded");
+}
+
 #[test]
 fn commit_amend_with_all_flag() {
     let repo = TestRepo::new("main");
@@ -269,6 +325,208 @@ fn commit_message_can_have_special_characters() {
     assert_eq!(last_commit_message(repo.path()), message);
 }
 
+#[test]
+fn commit_opens_editor_with_template() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let tools = TempDir::new().expect("temp dir");
+    let editor = fake_editor(&tools, "Message from editor\n\n# dropped comment\nBody line\n");
+
+    pk_cmd()
+        .args(["bc", "feature/edited"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    fs::write(repo.path().join("template.txt"), "Subject goes here\n").expect("write template");
+    run_git(repo.path(), &["config", "commit.template", "template.txt"]);
+
+    fs::write(repo.path().join("new.txt"), "content").expect("write file");
+    run_git(repo.path(), &["add", "new.txt"]);
+
+    pk_cmd()
+        .args(["commit"])
+        .current_dir(repo.path())
+        .env("GIT_EDITOR", &editor)
+        .env("XDG_CONFIG_HOME", tools.path())
+        .assert()
+        .success()
+        .stdout(contains("Created commit on branch 'feature/edited'"));
+
+    let seen = fs::read_to_string(tools.path().join("seen.txt")).expect("editor should run");
+    assert!(seen.starts_with("Subject goes here\n"), "template: {seen}");
+    assert!(seen.contains("# On branch feature/edited (1 of 1 in stack)"), "template: {seen}");
+    assert!(seen.contains("new file:   new.txt"), "template: {seen}");
+
+    let output = StdCommand::new("git")
+        .args(["log", "-1", "--pretty=%B"])
+        .current_dir(repo.path())
+        .output()
+        .expect("git log");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "Message from editor\n\nBody line"
+    );
+}
+
+#[test]
+fn commit_prefers_pancake_editor_setting() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let tools = TempDir::new().expect("temp dir");
+    let editor = fake_editor(&tools, "From pancake editor\n");
+
+    fs::create_dir_all(tools.path().join("pancake")).expect("create config dir");
+    fs::write(
+        tools.path().join("pancake/config.toml"),
+        format!("[defaults]\neditor = \"{}\"\n", editor),
+    )
+    .expect("write global config");
+
+    fs::write(repo.path().join("new.txt"), "content").expect("write file");
+    run_git(repo.path(), &["add", "new.txt"]);
+
+    pk_cmd()
+        .args(["commit"])
+        .current_dir(repo.path())
+        .env("GIT_EDITOR", "false")
+        .env("XDG_CONFIG_HOME", tools.path())
+        .assert()
+        .success();
+
+    assert_eq!(last_commit_message(repo.path()), "From pancake editor");
+}
+
+#[test]
+fn commit_with_paths_commits_only_those_paths() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("named.txt"), "named").expect("write file");
+    fs::write(repo.path().join("other.txt"), "other").expect("write file");
+    run_git(repo.path(), &["add", "named.txt", "other.txt"]);
+    run_git(repo.path(), &["commit", "-m", "add files"]);
+    fs::write(repo.path().join("staged.txt"), "staged").expect("write file");
+    fs::write(repo.path().join("named.txt"), "named v2").expect("write file");
+    fs::write(repo.path().join("other.txt"), "other v2").expect("write file");
+    run_git(repo.path(), &["add", "staged.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-m", "Only the named file", "named.txt"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        git_output(repo.path(), &["show", "--name-only", "--pretty=", "HEAD"]),
+        "named.txt\n"
+    );
+    // Other staged changes stay staged for the next commit
+    assert_eq!(
+        git_output(repo.path(), &["diff", "--cached", "--name-only"]),
+        "staged.txt\n"
+    );
+}
+
+#[test]
+fn commit_with_directory_path_skips_untracked_files() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::create_dir(repo.path().join("src")).expect("create dir");
+    fs::write(repo.path().join("src/lib.rs"), "v1").expect("write file");
+    run_git(repo.path(), &["add", "src/lib.rs"]);
+    run_git(repo.path(), &["commit", "-m", "add lib"]);
+    fs::write(repo.path().join("src/lib.rs"), "v2").expect("write file");
+    fs::write(repo.path().join("src/junk.tmp"), "junk").expect("write file");
+
+    pk_cmd()
+        .args(["commit", "-m", "Update lib", "src"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        git_output(repo.path(), &["show", "--name-only", "--pretty=", "HEAD"]),
+        "src/lib.rs\n"
+    );
+    assert_eq!(
+        git_output(repo.path(), &["status", "--porcelain", "src"]),
+        "?? src/junk.tmp\n"
+    );
+}
+
+#[test]
+fn commit_with_untracked_path_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("new.txt"), "new").expect("write file");
+
+    pk_cmd()
+        .args(["commit", "-m", "Untracked", "new.txt"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("pathspec 'new.txt' did not match any files known to git"));
+}
+
+#[test]
+fn commit_with_unknown_path_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["commit", "-m", "Missing", "missing.txt"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("pathspec 'missing.txt' did not match any files"));
+}
+
+#[test]
+fn commit_patch_stages_selected_hunks() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let lines: Vec<String> = (1..=20).map(|n| format!("line {n}\n")).collect();
+    fs::write(repo.path().join("lines.txt"), lines.concat()).expect("write file");
+    run_git(repo.path(), &["add", "lines.txt"]);
+    run_git(repo.path(), &["commit", "-m", "add lines"]);
+
+    let mut changed = lines.clone();
+    changed[0] = "first changed\n".to_string();
+    changed[19] = "last changed\n".to_string();
+    fs::write(repo.path().join("lines.txt"), changed.concat()).expect("write file");
+
+    // Take the first hunk, skip the second
+    pk_cmd()
+        .args(["commit", "-p", "-m", "First hunk only"])
+        .current_dir(repo.path())
+        .write_stdin("y\nn\n")
+        .assert()
+        .success();
+
+    let committed = git_output(repo.path(), &["show", "HEAD:lines.txt"]);
+    assert!(committed.starts_with("first changed\n"));
+    assert!(committed.ends_with("line 20\n"));
+    assert_eq!(
+        git_output(repo.path(), &["diff", "--name-only"]),
+        "lines.txt\n"
+    );
+}
+
+#[test]
+fn commit_patch_without_selection_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("README.md"), "# Changed").expect("write file");
+
+    pk_cmd()
+        .args(["commit", "-p", "-m", "Nothing picked"])
+        .current_dir(repo.path())
+        .write_stdin("n\n")
+        .assert()
+        .failure()
+        .stderr(contains("No changes selected"));
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -291,6 +549,23 @@ impl TestRepo {
     }
 }
 
+/// Writes an editor script that saves the template it was given to
+/// `seen.txt` and replaces it with `message`.
+fn fake_editor(tools: &TempDir, message: &str) -> String {
+    let script = tools.path().join("editor.sh");
+    let seen = tools.path().join("seen.txt");
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\ncp \"$1\" '{}'\nprintf '%s' '{}' > \"$1\"\n",
+            seen.display(),
+            message
+        ),
+    )
+    .expect("write editor script");
+    format!("sh {}", script.display())
+}
+
 fn init_pk(repo: &TestRepo) {
     pk_cmd()
         .arg("init")
@@ -339,6 +614,16 @@ fn last_commit_message(dir: &Path) -> String {
     String::from_utf8_lossy(&output.stdout).trim().to_string()
 }
 
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
 fn run_git(dir: &Path, args: &[&str]) {
     let status = StdCommand::new("git")
         .args(args)
@@ -0,0 +1,269 @@
+use std::{fs, os::unix::fs::PermissionsExt, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn failing_pre_commit_hook_aborts_commit() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    write_hook(&repo.path().join(".git/hooks"), "pre-commit", "echo 'lint failed' >&2\nexit 1");
+
+    stage_file(&repo, "file.txt", "content");
+    let before = commit_count(repo.path());
+
+    pk_cmd()
+        .args(["commit", "-m", "blocked"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("lint failed"))
+        .stderr(contains("The pre-commit hook failed"))
+        .stderr(contains("--no-verify"));
+
+    assert_eq!(commit_count(repo.path()), before);
+
+    pk_cmd()
+        .args(["commit", "-m", "skipped", "--no-verify"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_count(repo.path()), before + 1);
+}
+
+#[test]
+fn message_hooks_can_rewrite_the_message() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let hooks = repo.path().join(".git/hooks");
+    write_hook(
+        &hooks,
+        "prepare-commit-msg",
+        "echo \"$2\" > \"$PK_TEST_OUTPUT_DIR/source.txt\"",
+    );
+    write_hook(&hooks, "commit-msg", "printf '%s\\n\\nReviewed-by: hook\\n' \"$(cat \"$1\")\" > \"$1\"");
+
+    stage_file(&repo, "file.txt", "content");
+
+    pk_cmd()
+        .args(["commit", "-m", "original"])
+        .current_dir(repo.path())
+        .env("PK_TEST_OUTPUT_DIR", repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(repo.path().join("source.txt")).unwrap().trim(),
+        "message"
+    );
+    assert_eq!(
+        git_output(repo.path(), &["log", "-1", "--pretty=%B"]),
+        "original\n\nReviewed-by: hook"
+    );
+}
+
+#[test]
+fn pre_commit_hook_changes_are_part_of_path_limited_commit() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    stage_file(&repo, "file.txt", "original\n");
+    run_git(repo.path(), &["commit", "-m", "add file"]);
+    // A formatter that rewrites the file and stages the result
+    write_hook(
+        &repo.path().join(".git/hooks"),
+        "pre-commit",
+        "echo formatted > file.txt\ngit add file.txt",
+    );
+    fs::write(repo.path().join("file.txt"), "changed\n").expect("write file");
+
+    pk_cmd()
+        .args(["commit", "-m", "path limited", "file.txt"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(git_output(repo.path(), &["show", "HEAD:file.txt"]), "formatted");
+}
+
+#[test]
+fn post_commit_hook_runs_after_commit() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    write_hook(
+        &repo.path().join(".git/hooks"),
+        "post-commit",
+        "git log -1 --pretty=%s > post-commit.txt",
+    );
+
+    stage_file(&repo, "file.txt", "content");
+
+    pk_cmd()
+        .args(["commit", "-m", "observed", "--no-verify"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        fs::read_to_string(repo.path().join("post-commit.txt")).unwrap().trim(),
+        "observed"
+    );
+}
+
+#[test]
+fn hooks_path_config_is_honored() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    write_hook(&repo.path().join("custom-hooks"), "pre-commit", "exit 1");
+    run_git(repo.path(), &["config", "core.hooksPath", "custom-hooks"]);
+
+    stage_file(&repo, "file.txt", "content");
+
+    pk_cmd()
+        .args(["commit", "-m", "blocked"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("The pre-commit hook failed"));
+}
+
+#[test]
+fn branch_create_with_message_runs_hooks() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    write_hook(&repo.path().join(".git/hooks"), "pre-commit", "exit 1");
+
+    stage_file(&repo, "file.txt", "content");
+
+    pk_cmd()
+        .args(["bc", "feature/hooked", "-m", "blocked"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("The pre-commit hook failed"));
+
+    pk_cmd()
+        .args(["bc", "feature/unhooked", "-m", "skipped", "--no-verify"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created commit on branch 'feature/unhooked'"));
+}
+
+#[test]
+fn branch_create_with_message_leaves_nothing_behind_when_commit_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    write_hook(&repo.path().join(".git/hooks"), "commit-msg", "exit 1");
+
+    stage_file(&repo, "file.txt", "content");
+    let before = commit_count(repo.path());
+
+    pk_cmd()
+        .args(["bc", "feature/rejected", "-m", "rejected"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("The commit-msg hook failed"));
+
+    assert_eq!(current_branch(repo.path()), "main");
+    assert_eq!(git_output(repo.path(), &["branch", "--list", "feature/rejected"]), "");
+    let stacks = fs::read_to_string(repo.path().join(".pancake/stacks.json")).unwrap_or_default();
+    assert!(!stacks.contains("feature/rejected"));
+    assert_eq!(commit_count(repo.path()), before);
+    // The staged work is still there to retry with
+    assert_eq!(
+        git_output(repo.path(), &["diff", "--cached", "--name-only"]),
+        "file.txt"
+    );
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_hook(dir: &Path, name: &str, body: &str) {
+    fs::create_dir_all(dir).expect("create hooks dir");
+    let path = dir.join(name);
+    fs::write(&path, format!("#!/bin/sh\n{body}\n")).expect("write hook");
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("chmod hook");
+}
+
+fn stage_file(repo: &TestRepo, filename: &str, contents: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+}
+
+fn commit_count(dir: &Path) -> usize {
+    git_output(dir, &["rev-list", "--count", "HEAD"])
+        .parse()
+        .expect("parse commit count")
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
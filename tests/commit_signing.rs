@@ -0,0 +1,313 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn commit_signs_with_ssh_when_gpgsign_is_set() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let keys = TempDir::new().expect("temp dir");
+    configure_ssh_signing(&repo, &keys);
+
+    fs::write(repo.path().join("signed.txt"), "signed").expect("write file");
+    run_git(repo.path(), &["add", "signed.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-m", "Signed commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert!(is_signed(repo.path(), "HEAD"));
+    assert!(verify_commit(repo.path(), "HEAD"));
+}
+
+#[test]
+fn commit_no_gpg_sign_overrides_config() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let keys = TempDir::new().expect("temp dir");
+    configure_ssh_signing(&repo, &keys);
+
+    fs::write(repo.path().join("unsigned.txt"), "unsigned").expect("write file");
+    run_git(repo.path(), &["add", "unsigned.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--no-gpg-sign", "-m", "Unsigned commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert!(!is_signed(repo.path(), "HEAD"));
+}
+
+#[test]
+fn commit_gpg_sign_uses_gpg_program_and_key() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let tools = TempDir::new().expect("temp dir");
+    let gpg = fake_gpg(&tools, true);
+    run_git(repo.path(), &["config", "gpg.program", &gpg]);
+
+    fs::write(repo.path().join("signed.txt"), "signed").expect("write file");
+    run_git(repo.path(), &["add", "signed.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--gpg-sign=ABCD1234", "-m", "Signed commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert!(
+        git_output(repo.path(), &["cat-file", "commit", "HEAD"])
+            .contains("gpgsig -----BEGIN PGP SIGNATURE-----")
+    );
+    let args = fs::read_to_string(tools.path().join("args.txt")).expect("read args");
+    assert_eq!(args.trim(), "--status-fd=2 -bsau ABCD1234");
+}
+
+#[test]
+fn commit_fails_when_signing_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let tools = TempDir::new().expect("temp dir");
+    let gpg = fake_gpg(&tools, false);
+    run_git(repo.path(), &["config", "gpg.program", &gpg]);
+    let before = git_output(repo.path(), &["rev-parse", "HEAD"]);
+
+    fs::write(repo.path().join("signed.txt"), "signed").expect("write file");
+    run_git(repo.path(), &["add", "signed.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-S", "-m", "Signed commit"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("failed to sign the commit"));
+
+    assert_eq!(git_output(repo.path(), &["rev-parse", "HEAD"]), before);
+}
+
+#[test]
+fn amend_and_restack_produce_signed_commits() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "base.txt", "base", "base commit");
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "top.txt", "top", "top commit");
+    run_git(repo.path(), &["checkout", "feature/base"]);
+
+    let keys = TempDir::new().expect("temp dir");
+    configure_ssh_signing(&repo, &keys);
+    fs::write(repo.path().join("base.txt"), "base amended").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["amend"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert!(verify_commit(repo.path(), "feature/base"));
+    assert!(verify_commit(repo.path(), "feature/top"));
+}
+
+#[test]
+fn rewritten_commits_are_signed() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "base.txt", "base", "base commit");
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "one.txt", "one", "first top commit");
+    write_and_commit(&repo, "two.txt", "two", "second top commit");
+
+    let keys = TempDir::new().expect("temp dir");
+    configure_ssh_signing(&repo, &keys);
+
+    pk_cmd()
+        .args(["move", "HEAD", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert!(verify_commit(repo.path(), "feature/base"));
+    assert!(verify_commit(repo.path(), "feature/top"));
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+        run_git(dir.path(), &["config", "user.name", "Pancake"]);
+        run_git(dir.path(), &["config", "user.email", "pancake@example.com"]);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Generates an SSH key and configures the repository to sign every commit
+/// with it, trusting the key for `git verify-commit`.
+fn configure_ssh_signing(repo: &TestRepo, keys: &TempDir) {
+    let key = keys.path().join("id_ed25519");
+    let status = StdCommand::new("ssh-keygen")
+        .args(["-q", "-t", "ed25519", "-N", "", "-C", "pancake", "-f"])
+        .arg(&key)
+        .status()
+        .expect("run ssh-keygen");
+    assert!(status.success(), "ssh-keygen failed");
+
+    let public_key = fs::read_to_string(key.with_extension("pub")).expect("read public key");
+    let allowed = keys.path().join("allowed_signers");
+    fs::write(
+        &allowed,
+        format!("pancake@example.com {}", public_key.trim()),
+    )
+    .expect("write allowed signers");
+
+    run_git(repo.path(), &["config", "gpg.format", "ssh"]);
+    run_git(
+        repo.path(),
+        &["config", "user.signingkey", key.to_str().unwrap()],
+    );
+    run_git(
+        repo.path(),
+        &[
+            "config",
+            "gpg.ssh.allowedSignersFile",
+            allowed.to_str().unwrap(),
+        ],
+    );
+    run_git(repo.path(), &["config", "commit.gpgsign", "true"]);
+}
+
+/// Writes a stand-in for gpg that records its arguments and emits a fixed
+/// signature, failing when `succeed` is false.
+fn fake_gpg(tools: &TempDir, succeed: bool) -> String {
+    let script = tools.path().join("gpg.sh");
+    let args = tools.path().join("args.txt");
+    let body = if succeed {
+        "echo '[GNUPG:] KEY_CONSIDERED' >&2\necho '[GNUPG:] SIG_CREATED D 22 8 00' >&2\nprintf -- '-----BEGIN PGP SIGNATURE-----\\nfake\\n-----END PGP SIGNATURE-----\\n'\n"
+    } else {
+        "echo 'gpg: signing failed: No secret key' >&2\nexit 2\n"
+    };
+    fs::write(
+        &script,
+        format!(
+            "#!/bin/sh\necho \"$@\" > '{}'\ncat > /dev/null\n{}",
+            args.display(),
+            body
+        ),
+    )
+    .expect("write gpg script");
+    let status = StdCommand::new("chmod")
+        .arg("+x")
+        .arg(&script)
+        .status()
+        .expect("chmod");
+    assert!(status.success());
+    script.display().to_string()
+}
+
+fn is_signed(dir: &Path, rev: &str) -> bool {
+    git_output(dir, &["cat-file", "commit", rev]).contains("\ngpgsig ")
+}
+
+fn verify_commit(dir: &Path, rev: &str) -> bool {
+    StdCommand::new("git")
+        .args(["verify-commit", rev])
+        .current_dir(dir)
+        .output()
+        .expect("git verify-commit")
+        .status
+        .success()
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
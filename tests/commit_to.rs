@@ -0,0 +1,294 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn commit_to_lower_branch_restacks_the_stack() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    fs::write(repo.path().join("README.md"), "# Top readme").expect("write file");
+    run_git(repo.path(), &["commit", "-am", "top readme"]);
+
+    fs::write(repo.path().join("base.txt"), "one\ntwo fixed\nthree\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--to", "feature/base", "-m", "fix base"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Created commit on branch 'feature/base'"))
+        .stdout(contains("Restacked 1 branch(es): feature/top"));
+
+    let subject = git_output(repo.path(), &["log", "-1", "--format=%s", "feature/base"]);
+    assert_eq!(subject.trim(), "fix base");
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 2);
+    // Only the staged change lands on the lower branch
+    assert_eq!(
+        show_file(repo.path(), "feature/base", "base.txt"),
+        "one\ntwo fixed\nthree\n"
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/base", "README.md"),
+        "# Test repo"
+    );
+
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "feature/base"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 2);
+    assert_eq!(current_branch(repo.path()), "feature/top");
+    assert_eq!(
+        git_output(
+            repo.path(),
+            &["status", "--porcelain", "--untracked-files=no"]
+        ),
+        ""
+    );
+}
+
+#[test]
+fn commit_to_keeps_unstaged_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+
+    fs::write(repo.path().join("base.txt"), "one\ntwo\nthree\nfour\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    fs::write(repo.path().join("top.txt"), "four\nfive\nsix\nseven\n").expect("write file");
+
+    pk_cmd()
+        .args(["commit", "--to", "feature/base", "-m", "extend base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "base.txt"),
+        "one\ntwo\nthree\nfour\n"
+    );
+    assert_eq!(
+        show_file(repo.path(), "feature/top", "top.txt"),
+        "four\nfive\nsix\n"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("top.txt")).expect("read file"),
+        "four\nfive\nsix\nseven\n"
+    );
+    assert_eq!(
+        git_output(
+            repo.path(),
+            &["status", "--porcelain", "--untracked-files=no"]
+        ),
+        " M top.txt\n"
+    );
+}
+
+#[test]
+fn commit_to_requires_a_lower_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    run_git(repo.path(), &["checkout", "feature/base"]);
+    fs::write(repo.path().join("base.txt"), "changed").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--to", "feature/top", "-m", "wrong way"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Branch 'feature/top' is not below 'feature/base' in the stack",
+        ));
+
+    pk_cmd()
+        .args(["commit", "--to", "main", "-m", "untracked"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Branch 'main' is not below 'feature/base' in the stack",
+        ));
+}
+
+#[test]
+fn commit_to_refuses_changes_that_do_not_apply() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    write_and_commit(&repo, "base.txt", "one\nTWO\nthree\n", "shout two");
+    let base_tip = rev_parse(repo.path(), "feature/base");
+
+    fs::write(repo.path().join("base.txt"), "one\n2\nthree\n").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--to", "feature/base", "-m", "number two"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("do not apply cleanly to 'feature/base'"));
+
+    assert_eq!(rev_parse(repo.path(), "feature/base"), base_tip);
+    assert_eq!(
+        git_output(repo.path(), &["diff", "--cached", "--name-only"]),
+        "base.txt\n"
+    );
+}
+
+#[test]
+fn commit_to_refuses_frozen_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_stack(&repo);
+    pk_cmd()
+        .args(["branch", "freeze", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    fs::write(repo.path().join("base.txt"), "changed").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+
+    pk_cmd()
+        .args(["commit", "--to", "feature/base", "-m", "frozen"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Branch 'feature/base' is frozen"));
+}
+
+#[test]
+fn commit_continue_without_operation_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["commit", "--continue"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("No commit operation is currently in progress"));
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Builds `main -> feature/base -> feature/top`, each branch adding one
+/// three-line file, and leaves `feature/top` checked out.
+fn create_stack(repo: &TestRepo) {
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "base.txt", "one\ntwo\nthree\n", "base commit");
+
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "top.txt", "four\nfive\nsix\n", "top commit");
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    git_output(dir, &["merge-base", left, right])
+        .trim()
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn commit_count(dir: &Path, range: &str) -> usize {
+    git_output(dir, &["rev-list", "--count", range])
+        .trim()
+        .parse()
+        .expect("parse commit count")
+}
+
+fn show_file(dir: &Path, rev: &str, path: &str) -> String {
+    git_output(dir, &["show", &format!("{rev}:{path}")])
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
@@ -0,0 +1,326 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn commit_adds_trailers_signoff_and_co_authors() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("file.txt"), "file").expect("write file");
+    run_git(repo.path(), &["add", "file.txt"]);
+
+    pk_cmd()
+        .args([
+            "commit",
+            "-m",
+            "Add file",
+            "--trailer",
+            "Reviewed-by=Alex <alex@example.com>",
+            "--trailer",
+            "Fixes: #12",
+            "--co-author",
+            "Sam <sam@example.com>",
+            "--signoff",
+        ])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        commit_message(repo.path(), "HEAD"),
+        "Add file\n\nReviewed-by: Alex <alex@example.com>\nFixes: #12\nCo-authored-by: Sam <sam@example.com>\nSigned-off-by: Pancake <pancake@example.com>"
+    );
+}
+
+#[test]
+fn commit_extends_existing_trailer_block_without_duplicates() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("file.txt"), "file").expect("write file");
+    run_git(repo.path(), &["add", "file.txt"]);
+
+    pk_cmd()
+        .args([
+            "commit",
+            "-m",
+            "Add file\n\nSigned-off-by: Pancake <pancake@example.com>",
+            "-s",
+            "--trailer",
+            "Fixes=#12",
+        ])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        commit_message(repo.path(), "HEAD"),
+        "Add file\n\nSigned-off-by: Pancake <pancake@example.com>\nFixes: #12"
+    );
+}
+
+#[test]
+fn commit_rejects_malformed_trailers() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    fs::write(repo.path().join("file.txt"), "file").expect("write file");
+    run_git(repo.path(), &["add", "file.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-m", "Add file", "--trailer", "no value"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Invalid trailer 'no value'"));
+
+    pk_cmd()
+        .args(["commit", "-m", "Add file", "--co-author", "sam@example.com"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Invalid co-author 'sam@example.com'"));
+}
+
+#[test]
+fn change_ids_are_off_by_default() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    pk_cmd()
+        .args(["bc", "feature/plain"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    fs::write(repo.path().join("file.txt"), "file").expect("write file");
+    run_git(repo.path(), &["add", "file.txt"]);
+
+    pk_cmd()
+        .args(["commit", "-m", "Add file"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_message(repo.path(), "HEAD"), "Add file");
+    assert!(!repo.path().join(".pancake/changes.json").exists());
+}
+
+#[test]
+fn change_id_survives_amend_and_restack() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    enable_change_ids(&repo);
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    fs::write(repo.path().join("base.txt"), "base").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args(["commit", "-m", "Add base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    let base_id = change_id(repo.path(), "HEAD");
+    assert!(base_id.starts_with('I') && base_id.len() == 41);
+
+    fs::write(repo.path().join("top.txt"), "top").expect("write file");
+    run_git(repo.path(), &["add", "top.txt"]);
+    pk_cmd()
+        .args(["bc", "feature/top", "-m", "Add top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    let top_id = change_id(repo.path(), "HEAD");
+    assert_ne!(top_id, base_id);
+
+    run_git(repo.path(), &["checkout", "feature/base"]);
+    fs::write(repo.path().join("base.txt"), "base amended").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args(["amend", "-m", "Add base, reworded"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(change_id(repo.path(), "feature/base"), base_id);
+    assert_eq!(change_id(repo.path(), "feature/top"), top_id);
+    assert_eq!(
+        recorded_change(repo.path(), &base_id),
+        (
+            "feature/base".to_string(),
+            rev_parse(repo.path(), "feature/base")
+        )
+    );
+    assert_eq!(
+        recorded_change(repo.path(), &top_id),
+        (
+            "feature/top".to_string(),
+            rev_parse(repo.path(), "feature/top")
+        )
+    );
+}
+
+#[test]
+fn change_index_follows_moved_commits() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    enable_change_ids(&repo);
+    fs::write(repo.path().join("base.txt"), "base").expect("write file");
+    run_git(repo.path(), &["add", "base.txt"]);
+    pk_cmd()
+        .args(["bc", "feature/base", "-m", "Add base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    fs::write(repo.path().join("one.txt"), "one").expect("write file");
+    run_git(repo.path(), &["add", "one.txt"]);
+    pk_cmd()
+        .args(["commit", "-m", "First top commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    fs::write(repo.path().join("two.txt"), "two").expect("write file");
+    run_git(repo.path(), &["add", "two.txt"]);
+    pk_cmd()
+        .args(["commit", "-m", "Second top commit"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    let moved_id = change_id(repo.path(), "HEAD");
+    assert_eq!(recorded_change(repo.path(), &moved_id).0, "feature/top");
+
+    pk_cmd()
+        .args(["move", "HEAD", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(change_id(repo.path(), "feature/base"), moved_id);
+    assert_eq!(
+        recorded_change(repo.path(), &moved_id),
+        (
+            "feature/base".to_string(),
+            rev_parse(repo.path(), "feature/base")
+        )
+    );
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+        run_git(dir.path(), &["config", "user.name", "Pancake"]);
+        run_git(dir.path(), &["config", "user.email", "pancake@example.com"]);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+fn enable_change_ids(repo: &TestRepo) {
+    let path = repo.path().join(".pancake/config");
+    let config = fs::read_to_string(&path).expect("read config");
+    assert!(config.contains("change_id = false"));
+    fs::write(
+        &path,
+        config.replace("change_id = false", "change_id = true"),
+    )
+    .expect("write config");
+}
+
+fn commit_message(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["log", "-1", "--format=%B", rev])
+        .trim_end()
+        .to_string()
+}
+
+fn change_id(dir: &Path, rev: &str) -> String {
+    commit_message(dir, rev)
+        .lines()
+        .find_map(|line| line.strip_prefix("Change-Id: "))
+        .expect("commit should have a Change-Id")
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn recorded_change(dir: &Path, id: &str) -> (String, String) {
+    let raw = fs::read_to_string(dir.join(".pancake/changes.json")).expect("read changes");
+    let doc: serde_json::Value = serde_json::from_str(&raw).expect("parse changes");
+    let change = &doc["changes"][id];
+    (
+        change["branch"].as_str().expect("branch").to_string(),
+        change["commit"].as_str().expect("commit").to_string(),
+    )
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
@@ -100,6 +100,38 @@ fn log_short_view_lists_paths() {
         .stdout(contains("main -> bugfix/hotfix\nmain -> feature/first -> feature/second"));
 }
 
+#[test]
+fn log_shows_remote_parent_as_external_root() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let remote = TempDir::new().expect("temp dir");
+    run_git(remote.path(), &["init", "--bare"]);
+    run_git(
+        repo.path(),
+        &["remote", "add", "origin", remote.path().to_str().unwrap()],
+    );
+    run_git(repo.path(), &["checkout", "-b", "teammate"]);
+    fs::write(repo.path().join("teammate.txt"), "teammate").expect("write file");
+    run_git(repo.path(), &["add", "teammate.txt"]);
+    run_git(repo.path(), &["commit", "-m", "teammate work"]);
+    run_git(repo.path(), &["push", "origin", "teammate"]);
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["branch", "-D", "teammate"]);
+
+    pk_cmd()
+        .args(["bc", "feature/on-teammate", "--base", "origin/teammate"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .arg("log")
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("origin/teammate (remote)\n`-- feature/on-teammate"));
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -0,0 +1,317 @@
+use std::{fs, path::Path, process::Command as StdCommand};
+
+use predicates::str::contains;
+use tempfile::TempDir;
+
+#[test]
+fn move_commit_down_to_ancestor() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base.txt"]);
+    create_branch(&repo, "feature/top", &["top-one.txt", "top-two.txt"]);
+    let commit = rev_parse(repo.path(), "HEAD");
+
+    pk_cmd()
+        .args(["move", &commit, "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Moved 1 commit(s) from 'feature/top' to 'feature/base'",
+        ));
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 2);
+    assert!(has_file(repo.path(), "feature/base", "top-two.txt"));
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 1);
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "feature/base"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    assert_eq!(current_branch(repo.path()), "feature/top");
+    assert!(repo.path().join("top-two.txt").exists());
+}
+
+#[test]
+fn move_commit_up_to_descendant() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base-one.txt", "base-two.txt"]);
+    create_branch(&repo, "feature/top", &["top.txt"]);
+
+    pk_cmd()
+        .args([
+            "mv",
+            "feature/base",
+            "--from",
+            "feature/base",
+            "--to",
+            "feature/top",
+        ])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 1);
+    assert!(!has_file(repo.path(), "feature/base", "base-two.txt"));
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/top"), 2);
+    assert_eq!(
+        git_output(repo.path(), &["log", "-1", "--pretty=%s", "feature/top"]),
+        "add base-two.txt\n"
+    );
+    assert!(has_file(repo.path(), "feature/top", "top.txt"));
+}
+
+#[test]
+fn move_commit_between_sibling_branches() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/a", &["a-one.txt", "a-two.txt"]);
+    run_git(repo.path(), &["checkout", "main"]);
+    create_branch(&repo, "feature/b", &["b.txt"]);
+    run_git(repo.path(), &["checkout", "feature/a"]);
+
+    pk_cmd()
+        .args(["move", "HEAD", "--to", "feature/b"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(commit_count(repo.path(), "main..feature/a"), 1);
+    assert_eq!(commit_count(repo.path(), "main..feature/b"), 2);
+    assert!(has_file(repo.path(), "feature/b", "a-two.txt"));
+    assert!(!repo.path().join("a-two.txt").exists());
+}
+
+#[test]
+fn move_refuses_to_overwrite_untracked_files() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/a", &["a-one.txt", "a-two.txt"]);
+    let commit = rev_parse(repo.path(), "feature/a");
+    run_git(repo.path(), &["checkout", "main"]);
+    create_branch(&repo, "feature/b", &["b.txt"]);
+    let b_tip = rev_parse(repo.path(), "feature/b");
+    fs::write(repo.path().join("a-two.txt"), "local notes\n").expect("write untracked file");
+
+    pk_cmd()
+        .args(["move", &commit, "--from", "feature/a", "--to", "feature/b"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("nothing was changed"));
+
+    assert_eq!(
+        fs::read_to_string(repo.path().join("a-two.txt")).expect("read untracked file"),
+        "local notes\n"
+    );
+    assert_eq!(rev_parse(repo.path(), "feature/a"), commit);
+    assert_eq!(rev_parse(repo.path(), "feature/b"), b_tip);
+    assert_eq!(current_branch(repo.path()), "feature/b");
+}
+
+#[test]
+fn move_restacks_branches_above() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base.txt"]);
+    create_branch(&repo, "feature/mid", &["mid-one.txt", "mid-two.txt"]);
+    create_branch(&repo, "feature/child", &["child.txt"]);
+    run_git(repo.path(), &["checkout", "feature/mid"]);
+
+    // Moving the newer commit down rewrites the middle branch around it
+    pk_cmd()
+        .args(["move", "HEAD", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Rebasing 'feature/child' onto 'feature/mid'"))
+        .stdout(contains("Restacked 1 branch(es)"));
+
+    assert_eq!(commit_count(repo.path(), "main..feature/base"), 2);
+    assert!(has_file(repo.path(), "feature/base", "mid-two.txt"));
+    assert_eq!(commit_count(repo.path(), "feature/base..feature/mid"), 1);
+    assert_eq!(
+        merge_base(repo.path(), "feature/child", "feature/mid"),
+        rev_parse(repo.path(), "feature/mid")
+    );
+    assert_eq!(commit_count(repo.path(), "feature/mid..feature/child"), 1);
+    assert_eq!(current_branch(repo.path()), "feature/mid");
+}
+
+#[test]
+fn move_skips_branches_above_that_are_up_to_date() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base.txt"]);
+    create_branch(&repo, "feature/mid", &["mid.txt"]);
+    create_branch(&repo, "feature/child", &["child.txt"]);
+    run_git(repo.path(), &["checkout", "feature/mid"]);
+    let child = rev_parse(repo.path(), "feature/child");
+
+    // The only commit of the middle branch moves down unchanged
+    pk_cmd()
+        .args(["move", "HEAD", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Skipping 'feature/child': already up to date with 'feature/mid'",
+        ))
+        .stdout(contains("Already up to date: feature/child"));
+
+    assert_eq!(
+        rev_parse(repo.path(), "feature/mid"),
+        rev_parse(repo.path(), "feature/base")
+    );
+    assert_eq!(rev_parse(repo.path(), "feature/child"), child);
+}
+
+#[test]
+fn move_lists_commits_without_terminal() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base.txt"]);
+    create_branch(&repo, "feature/top", &["top.txt"]);
+
+    pk_cmd()
+        .args(["move", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stdout(contains("Commits on 'feature/top':"))
+        .stdout(contains("add top.txt"))
+        .stderr(contains("Pass the commits to move"));
+}
+
+#[test]
+fn move_rejects_commits_from_other_branches() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_branch(&repo, "feature/base", &["base.txt"]);
+    create_branch(&repo, "feature/top", &["top.txt"]);
+
+    pk_cmd()
+        .args(["move", "feature/base", "--to", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("does not belong to branch 'feature/top'"));
+}
+
+struct TestRepo {
+    dir: TempDir,
+}
+
+impl TestRepo {
+    fn new(default_branch: &str) -> Self {
+        let dir = TempDir::new().expect("temp dir");
+        run_git(dir.path(), &["init"]);
+        fs::write(dir.path().join("README.md"), "# Test repo").expect("write readme");
+        run_git(dir.path(), &["add", "README.md"]);
+        run_git(dir.path(), &["commit", "-m", "init"]);
+
+        checkout_branch(dir.path(), default_branch);
+
+        Self { dir }
+    }
+
+    fn path(&self) -> &Path {
+        self.dir.path()
+    }
+}
+
+/// Creates `branch` on top of the current branch with one commit per file.
+fn create_branch(repo: &TestRepo, branch: &str, files: &[&str]) {
+    pk_cmd()
+        .args(["bc", branch])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    for file in files {
+        write_and_commit(repo, file, file, &format!("add {file}"));
+    }
+}
+
+fn has_file(dir: &Path, rev: &str, path: &str) -> bool {
+    StdCommand::new("git")
+        .args(["cat-file", "-e", &format!("{rev}:{path}")])
+        .current_dir(dir)
+        .status()
+        .expect("git cat-file")
+        .success()
+}
+
+fn init_pk(repo: &TestRepo) {
+    pk_cmd()
+        .arg("init")
+        .current_dir(repo.path())
+        .assert()
+        .success();
+}
+
+fn write_and_commit(repo: &TestRepo, filename: &str, contents: &str, message: &str) {
+    fs::write(repo.path().join(filename), contents).expect("write file");
+    run_git(repo.path(), &["add", filename]);
+    run_git(repo.path(), &["commit", "-m", message]);
+}
+
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn merge_base(dir: &Path, left: &str, right: &str) -> String {
+    git_output(dir, &["merge-base", left, right])
+        .trim()
+        .to_string()
+}
+
+fn rev_parse(dir: &Path, rev: &str) -> String {
+    git_output(dir, &["rev-parse", rev]).trim().to_string()
+}
+
+fn commit_count(dir: &Path, range: &str) -> usize {
+    git_output(dir, &["rev-list", "--count", range])
+        .trim()
+        .parse()
+        .expect("parse commit count")
+}
+
+fn current_branch(dir: &Path) -> String {
+    git_output(dir, &["rev-parse", "--abbrev-ref", "HEAD"])
+        .trim()
+        .to_string()
+}
+
+fn checkout_branch(dir: &Path, branch: &str) {
+    if current_branch(dir) == branch {
+        return;
+    }
+    run_git(dir, &["checkout", "-b", branch]);
+}
+
+fn run_git(dir: &Path, args: &[&str]) {
+    let status = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env("GIT_AUTHOR_NAME", "Pancake")
+        .env("GIT_AUTHOR_EMAIL", "pancake@example.com")
+        .env("GIT_COMMITTER_NAME", "Pancake")
+        .env("GIT_COMMITTER_EMAIL", "pancake@example.com")
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+fn pk_cmd() -> assert_cmd::Command {
+    #[allow(deprecated)]
+    {
+        assert_cmd::Command::cargo_bin("pk").expect("pk binary")
+    }
+}
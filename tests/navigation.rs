@@ -1,6 +1,6 @@
 use std::{fs, path::Path, process::Command as StdCommand};
 
-use predicates::str::contains;
+use predicates::{prelude::*, str::contains};
 use tempfile::TempDir;
 
 #[test]
@@ -502,6 +502,431 @@ fn navigation_round_trip() {
     assert_eq!(current_branch(repo.path()), "feature/third");
 }
 
+#[test]
+fn up_pick_selects_child_by_number_or_name() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    pk_cmd()
+        .args(["up", "--pick", "2"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second-b'"));
+
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["up", "--pick", "feature/second-a"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second-a'"));
+
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["up", "--pick", "feature/missing"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("'feature/missing' is not one of the choices"));
+}
+
+#[test]
+fn up_with_count_asks_at_each_fork() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    // Add a single child above second-b so the walk continues past the fork
+    run_git(repo.path(), &["checkout", "feature/second-b"]);
+    pk_cmd()
+        .args(["bc", "feature/third"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["up", "3", "--pick", "feature/second-b"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/third'"));
+
+    assert_eq!(current_branch(repo.path()), "feature/third");
+}
+
+#[test]
+fn up_with_count_without_pick_lists_choices() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    run_git(repo.path(), &["checkout", "main"]);
+
+    pk_cmd()
+        .args(["up", "2"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stdout(contains("Branch 'feature/first' has multiple children"))
+        .stdout(contains("1: feature/second-a"))
+        .stdout(contains("2: feature/second-b"))
+        .stderr(contains("--pick"));
+
+    assert_eq!(current_branch(repo.path()), "main");
+}
+
+#[test]
+fn top_lists_every_leaf_when_forked() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    pk_cmd()
+        .args(["top"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stdout(contains("multiple tops"))
+        .stdout(contains("1: feature/second-a"))
+        .stdout(contains("2: feature/second-b"));
+
+    assert_eq!(current_branch(repo.path()), "feature/first");
+}
+
+#[test]
+fn top_without_terminal_suggests_its_own_flags() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    pk_cmd()
+        .args(["top"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("no terminal is attached"))
+        .stderr(contains("Use `--leaf <name>` or `--deepest` to select one."))
+        .stderr(contains("--pick").not());
+
+    assert_eq!(current_branch(repo.path()), "feature/first");
+}
+
+#[test]
+fn top_leaf_and_deepest_options() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    run_git(repo.path(), &["checkout", "feature/second-b"]);
+    pk_cmd()
+        .args(["bc", "feature/third"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["top", "--leaf", "feature/second-a"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second-a' (top of stack)"));
+
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["top", "--leaf", "feature/second-b"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("is not a top of the stack"));
+
+    pk_cmd()
+        .args(["top", "--deepest"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/third' (top of stack)"));
+}
+
+#[test]
+fn top_remembers_last_chosen_path() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    pk_cmd()
+        .args(["top", "--leaf", "feature/second-b"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["top"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second-b' (top of stack)"));
+}
+
+#[test]
+fn top_keeps_remembered_path_when_checkout_is_refused() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_fork(&repo);
+
+    run_git(repo.path(), &["checkout", "feature/second-a"]);
+    fs::write(repo.path().join("shared.txt"), "second-a\n").expect("write file");
+    run_git(repo.path(), &["add", "shared.txt"]);
+    run_git(repo.path(), &["commit", "-m", "feature/second-a"]);
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    pk_cmd()
+        .args(["top", "--leaf", "feature/second-b"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    run_git(repo.path(), &["checkout", "feature/first"]);
+
+    fs::write(repo.path().join("shared.txt"), "local\n").expect("write file");
+    pk_cmd()
+        .args(["top", "--leaf", "feature/second-a"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("would be overwritten"));
+    fs::remove_file(repo.path().join("shared.txt")).expect("remove file");
+
+    pk_cmd()
+        .args(["top"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second-b' (top of stack)"));
+}
+
+#[test]
+fn checkout_by_stack_position() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    for branch in ["feature/first", "feature/second", "feature/third"] {
+        pk_cmd()
+            .args(["bc", branch])
+            .current_dir(repo.path())
+            .assert()
+            .success();
+    }
+
+    pk_cmd()
+        .args(["co", "1"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/first' (1 of 3 in stack)"));
+
+    pk_cmd()
+        .args(["branch", "checkout", "3"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/third' (3 of 3 in stack)"));
+
+    pk_cmd()
+        .args(["co", "4"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Position 4 is out of range"));
+}
+
+#[test]
+fn checkout_dash_returns_to_previous_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/first"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    pk_cmd()
+        .args(["bc", "feature/second"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["down"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["co", "-"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/second'"));
+
+    pk_cmd()
+        .args(["co", "-"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/first'"));
+}
+
+#[test]
+fn checkout_dash_without_history_fails() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["co", "-"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("No previous branch recorded"));
+}
+
+#[test]
+fn trunk_navigates_to_main_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/first"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["trunk"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'main'"));
+    assert_eq!(current_branch(repo.path()), "main");
+
+    pk_cmd()
+        .args(["trunk"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Already on the main branch"));
+
+    pk_cmd()
+        .args(["co", "-"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Switched to branch 'feature/first'"));
+}
+
+#[test]
+fn down_refuses_to_overwrite_local_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_diverged_pair(&repo);
+
+    fs::write(repo.path().join("shared.txt"), "local edit").expect("write file");
+
+    pk_cmd()
+        .args(["down"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("would be overwritten by checking out 'feature/first'"))
+        .stderr(contains("shared.txt"))
+        .stderr(contains("--autostash"));
+
+    assert_eq!(current_branch(repo.path()), "feature/second");
+    assert_eq!(
+        fs::read_to_string(repo.path().join("shared.txt")).unwrap(),
+        "local edit"
+    );
+}
+
+#[test]
+fn down_carries_over_non_conflicting_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_diverged_pair(&repo);
+
+    fs::write(repo.path().join("README.md"), "local edit").expect("write file");
+
+    pk_cmd()
+        .args(["down"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(current_branch(repo.path()), "feature/first");
+    assert_eq!(
+        fs::read_to_string(repo.path().join("README.md")).unwrap(),
+        "local edit"
+    );
+}
+
+#[test]
+fn down_autostash_reapplies_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_diverged_pair(&repo);
+
+    fs::write(repo.path().join("README.md"), "local edit").expect("write file");
+    fs::write(repo.path().join("other.txt"), "stash me").expect("write file");
+    run_git(repo.path(), &["add", "other.txt"]);
+
+    pk_cmd()
+        .args(["down", "--autostash"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Stashed uncommitted changes"))
+        .stdout(contains("Re-applied stashed changes"));
+
+    assert_eq!(current_branch(repo.path()), "feature/first");
+    assert_eq!(
+        fs::read_to_string(repo.path().join("README.md")).unwrap(),
+        "local edit"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("other.txt")).unwrap(),
+        "stash me"
+    );
+}
+
+#[test]
+fn down_force_discards_conflicting_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_diverged_pair(&repo);
+
+    fs::write(repo.path().join("shared.txt"), "local edit").expect("write file");
+
+    pk_cmd()
+        .args(["down", "--force"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(current_branch(repo.path()), "feature/first");
+    assert_eq!(
+        fs::read_to_string(repo.path().join("shared.txt")).unwrap(),
+        "first\n"
+    );
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -524,6 +949,38 @@ impl TestRepo {
     }
 }
 
+/// Creates main -> feature/first -> {feature/second-a, feature/second-b} and
+/// leaves feature/first checked out.
+fn create_fork(repo: &TestRepo) {
+    for args in [
+        vec!["bc", "feature/first"],
+        vec!["bc", "feature/second-a"],
+        vec!["bc", "feature/second-b", "--base", "feature/first"],
+    ] {
+        pk_cmd()
+            .args(&args)
+            .current_dir(repo.path())
+            .assert()
+            .success();
+    }
+    run_git(repo.path(), &["checkout", "feature/first"]);
+}
+
+/// Creates main -> feature/first -> feature/second where both branches change
+/// `shared.txt`, and leaves feature/second checked out.
+fn create_diverged_pair(repo: &TestRepo) {
+    for (branch, contents) in [("feature/first", "first\n"), ("feature/second", "second\n")] {
+        pk_cmd()
+            .args(["bc", branch])
+            .current_dir(repo.path())
+            .assert()
+            .success();
+        fs::write(repo.path().join("shared.txt"), contents).expect("write file");
+        run_git(repo.path(), &["add", "shared.txt"]);
+        run_git(repo.path(), &["commit", "-m", branch]);
+    }
+}
+
 fn init_pk(repo: &TestRepo) {
     pk_cmd()
         .arg("init")
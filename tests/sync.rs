@@ -1,6 +1,6 @@
 use std::{fs, path::Path, process::Command as StdCommand};
 
-use predicates::str::contains;
+use predicates::{prelude::*, str::contains};
 use tempfile::TempDir;
 
 #[test]
@@ -163,6 +163,525 @@ fn restack_rebases_entire_stack() {
     assert_eq!(current_branch(repo.path()), "feature/beta");
 }
 
+#[test]
+fn sync_rebases_onto_fetched_remote_parent() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let remote = TempDir::new().expect("temp dir");
+    run_git(remote.path(), &["init", "--bare"]);
+    run_git(
+        repo.path(),
+        &["remote", "add", "origin", remote.path().to_str().unwrap()],
+    );
+    run_git(repo.path(), &["checkout", "-b", "teammate"]);
+    fs::write(repo.path().join("teammate.txt"), "teammate").expect("write file");
+    run_git(repo.path(), &["add", "teammate.txt"]);
+    run_git(repo.path(), &["commit", "-m", "teammate work"]);
+    run_git(repo.path(), &["push", "origin", "teammate"]);
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["branch", "-D", "teammate"]);
+
+    pk_cmd()
+        .args(["bc", "feature/on-teammate", "--base", "origin/teammate"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "mine.txt", "mine", "my work");
+
+    // The teammate pushes more work that we have not fetched yet
+    let other = TempDir::new().expect("temp dir");
+    run_git(
+        other.path(),
+        &["clone", "-b", "teammate", remote.path().to_str().unwrap(), "."],
+    );
+    fs::write(other.path().join("teammate.txt"), "teammate v2").expect("write file");
+    run_git(other.path(), &["commit", "-am", "teammate update"]);
+    run_git(other.path(), &["push", "origin", "teammate"]);
+    let teammate_tip = rev_parse(other.path(), "HEAD");
+
+    pk_cmd()
+        .args(["sync"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Fetching 'origin/teammate'"))
+        .stdout(contains("Rebasing 'feature/on-teammate' onto 'origin/teammate'"));
+
+    assert_eq!(rev_parse(repo.path(), "origin/teammate"), teammate_tip);
+    assert_eq!(
+        merge_base(repo.path(), "feature/on-teammate", "origin/teammate"),
+        teammate_tip
+    );
+}
+
+#[test]
+fn restack_rewrites_branches_without_checking_them_out() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/base"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "base.txt", "base", "base commit");
+
+    pk_cmd()
+        .args(["bc", "feature/top"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "top.txt", "top", "top commit");
+
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "README.md", "main updated", "main update");
+    run_git(repo.path(), &["checkout", "feature/top"]);
+    let checkouts = reflog_checkouts(repo.path());
+
+    // Local changes survive since the working tree is only updated in place
+    fs::write(repo.path().join("top.txt"), "top, in progress").expect("write file");
+
+    pk_cmd()
+        .args(["restack"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Restacked 2 branch(es)"));
+
+    assert_eq!(reflog_checkouts(repo.path()), checkouts);
+    assert_eq!(
+        merge_base(repo.path(), "feature/top", "main"),
+        rev_parse(repo.path(), "main")
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("README.md")).expect("read file"),
+        "main updated"
+    );
+    assert_eq!(
+        fs::read_to_string(repo.path().join("top.txt")).expect("read file"),
+        "top, in progress"
+    );
+    assert_eq!(
+        git_output(repo.path(), &["status", "--porcelain", "--untracked-files=no"]),
+        " M top.txt\n"
+    );
+}
+
+#[test]
+fn restack_drops_commits_already_in_parent() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/picked"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "picked.txt", "picked", "picked commit");
+    write_and_commit(&repo, "kept.txt", "kept", "kept commit");
+    let picked = rev_parse(repo.path(), "HEAD~1");
+
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["cherry-pick", &picked]);
+    run_git(repo.path(), &["checkout", "feature/picked"]);
+
+    pk_cmd()
+        .args(["restack"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(
+        git_output(repo.path(), &["log", "--format=%s", "main..feature/picked"]),
+        "kept commit\n"
+    );
+}
+
+#[test]
+fn sync_falls_back_to_worktree_rebase_on_conflict() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+
+    pk_cmd()
+        .args(["bc", "feature/conflict"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "README.md", "feature readme", "feature readme");
+
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "README.md", "main readme", "main readme");
+    run_git(repo.path(), &["checkout", "feature/conflict"]);
+
+    pk_cmd()
+        .args(["sync"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Resolve the conflicts"))
+        .stderr(contains("pk sync --continue"));
+
+    fs::write(repo.path().join("README.md"), "resolved readme").expect("write file");
+    run_git(repo.path(), &["add", "README.md"]);
+
+    pk_cmd()
+        .args(["sync", "--continue"])
+        .current_dir(repo.path())
+        .env("GIT_EDITOR", "true")
+        .assert()
+        .success()
+        .stdout(contains("Synced 1 branch(es)"));
+
+    assert_eq!(
+        merge_base(repo.path(), "feature/conflict", "main"),
+        rev_parse(repo.path(), "main")
+    );
+    assert_eq!(current_branch(repo.path()), "feature/conflict");
+}
+
+/// Builds `main -> feature/lower -> feature/upper` and checks out the upper branch.
+#[test]
+fn restack_skips_branches_that_are_up_to_date() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+    run_git(repo.path(), &["checkout", "feature/lower"]);
+    write_and_commit(&repo, "lower.txt", "two\n", "lower follow-up");
+    let lower = rev_parse(repo.path(), "feature/lower");
+    let checkouts = reflog_checkouts(repo.path());
+
+    pk_cmd()
+        .arg("restack")
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "Skipping 'feature/lower': already up to date with 'main'",
+        ))
+        .stdout(contains("Rebasing 'feature/upper' onto 'feature/lower'"))
+        .stdout(contains("Restacked 1 branch(es): feature/upper"))
+        .stdout(contains("Already up to date: feature/lower"));
+
+    assert_eq!(rev_parse(repo.path(), "feature/lower"), lower);
+    assert_eq!(merge_base(repo.path(), "feature/upper", "feature/lower"), lower);
+    assert_eq!(reflog_checkouts(repo.path()), checkouts);
+}
+
+#[test]
+fn restack_dry_run_predicts_conflicts_without_changes() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "lower.txt", "main\n", "main edits lower.txt");
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+
+    let lower = rev_parse(repo.path(), "feature/lower");
+    let upper = rev_parse(repo.path(), "feature/upper");
+    let objects = git_output(repo.path(), &["count-objects"]);
+    let status = git_output(repo.path(), &["status", "--porcelain"]);
+
+    pk_cmd()
+        .args(["restack", "--dry-run"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains(
+            "feature/lower onto main: conflicts in lower.txt while replaying",
+        ))
+        .stdout(contains(
+            "feature/upper onto feature/lower: unknown until 'feature/lower' is rebased",
+        ))
+        .stdout(contains("No branches or files were changed."));
+
+    assert_eq!(rev_parse(repo.path(), "feature/lower"), lower);
+    assert_eq!(rev_parse(repo.path(), "feature/upper"), upper);
+    assert_eq!(git_output(repo.path(), &["count-objects"]), objects);
+    assert_eq!(git_output(repo.path(), &["status", "--porcelain"]), status);
+    assert!(!repo.path().join(".pancake/operation_state.json").exists());
+}
+
+#[test]
+fn sync_dry_run_reports_clean_rebases() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "main.txt", "main", "main update");
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+    let lower = rev_parse(repo.path(), "feature/lower");
+
+    pk_cmd()
+        .args(["sync", "--all", "--dry-run"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Dry run: sync 2 branch(es)"))
+        .stdout(contains("feature/lower onto main: clean, 1 commit(s) replayed"))
+        .stdout(contains(
+            "feature/upper onto feature/lower: clean, 1 commit(s) replayed",
+        ))
+        .stdout(contains("Rebasing").not());
+
+    assert_eq!(rev_parse(repo.path(), "feature/lower"), lower);
+}
+
+#[test]
+fn restack_dry_run_reports_up_to_date_branches() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+
+    pk_cmd()
+        .args(["restack", "--dry-run"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("feature/lower onto main: up to date"))
+        .stdout(contains("feature/upper onto feature/lower: up to date"));
+}
+
+fn add_origin_with_main(repo: &TestRepo) -> TempDir {
+    let remote = TempDir::new().expect("temp dir");
+    run_git(remote.path(), &["init", "--bare"]);
+    run_git(
+        repo.path(),
+        &["remote", "add", "origin", remote.path().to_str().unwrap()],
+    );
+    run_git(repo.path(), &["push", "origin", "main"]);
+    remote
+}
+
+/// Pushes a new commit to `main` on the remote from a separate clone.
+fn push_to_remote_main(remote: &TempDir, filename: &str) -> String {
+    let other = TempDir::new().expect("temp dir");
+    run_git(
+        other.path(),
+        &["clone", "-b", "main", remote.path().to_str().unwrap(), "."],
+    );
+    fs::write(other.path().join(filename), "remote").expect("write file");
+    run_git(other.path(), &["add", filename]);
+    run_git(other.path(), &["commit", "-m", "remote work"]);
+    run_git(other.path(), &["push", "origin", "main"]);
+    rev_parse(other.path(), "HEAD")
+}
+
+#[test]
+fn sync_fetch_config_fast_forwards_trunk_and_restacks() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let remote = add_origin_with_main(&repo);
+    create_two_branch_stack(&repo);
+    let remote_tip = push_to_remote_main(&remote, "remote.txt");
+
+    let config_path = repo.path().join(".pancake/config");
+    let config = fs::read_to_string(&config_path).expect("read config");
+    fs::write(&config_path, config.replace("fetch = false", "fetch = true"))
+        .expect("write config");
+
+    pk_cmd()
+        .arg("sync")
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Fetching 'origin'"))
+        .stdout(contains("Fast-forwarded 'main' to 'origin/main'"))
+        .stdout(contains("Rebasing 'feature/lower' onto 'main'"))
+        .stdout(contains("Rebasing 'feature/upper' onto 'feature/lower'"));
+
+    assert_eq!(rev_parse(repo.path(), "main"), remote_tip);
+    assert_eq!(merge_base(repo.path(), "feature/lower", "main"), remote_tip);
+    assert_eq!(
+        merge_base(repo.path(), "feature/upper", "feature/lower"),
+        rev_parse(repo.path(), "feature/lower")
+    );
+    assert_eq!(current_branch(repo.path()), "feature/upper");
+}
+
+#[test]
+fn sync_fetch_reports_diverged_trunk() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    let remote = add_origin_with_main(&repo);
+    write_and_commit(&repo, "local.txt", "local", "local main work");
+    let local_tip = rev_parse(repo.path(), "main");
+    push_to_remote_main(&remote, "remote.txt");
+
+    pk_cmd()
+        .args(["bc", "feature/lower"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(&repo, "lower.txt", "lower", "lower commit");
+
+    pk_cmd()
+        .args(["sync", "--fetch"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stderr(contains("'main' has diverged from 'origin/main'"));
+
+    assert_eq!(rev_parse(repo.path(), "main"), local_tip);
+    assert_eq!(merge_base(repo.path(), "feature/lower", "main"), local_tip);
+}
+
+#[test]
+fn sync_fetch_requires_existing_remote() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    pk_cmd()
+        .args(["bc", "feature/lower"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    pk_cmd()
+        .args(["sync", "--fetch"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stderr(contains("Remote 'origin' does not exist"));
+}
+
+fn create_two_branch_stack(repo: &TestRepo) {
+    pk_cmd()
+        .args(["bc", "feature/lower"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "lower.txt", "one\n", "lower commit");
+
+    pk_cmd()
+        .args(["bc", "feature/upper"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+    write_and_commit(repo, "upper.txt", "upper\n", "upper commit");
+}
+
+#[test]
+fn sync_reparents_children_of_squash_merged_parent() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--squash", "feature/lower"]);
+    run_git(repo.path(), &["commit", "-m", "lower (squashed)"]);
+    write_and_commit(&repo, "README.md", "main moved on", "main update");
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+
+    pk_cmd()
+        .args(["sync"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Branch 'feature/lower' is merged into 'main'"))
+        .stdout(contains("Reparented 'feature/upper' onto 'main'"))
+        .stdout(contains("pk branch delete feature/lower"))
+        .stdout(contains("Synced 1 branch(es): feature/upper"))
+        .stdout(contains("Branch 'feature/upper' is merged").not());
+
+    assert_eq!(stack_parent(repo.path(), "feature/upper"), "main");
+    assert_eq!(
+        git_output(repo.path(), &["log", "--format=%s", "main..feature/upper"]),
+        "upper commit\n"
+    );
+    assert_eq!(
+        merge_base(repo.path(), "feature/upper", "main"),
+        rev_parse(repo.path(), "main")
+    );
+    // The merged branch is kept until it is deleted
+    assert_eq!(stack_parent(repo.path(), "feature/lower"), "main");
+}
+
+#[test]
+fn sync_abort_restores_parent_of_reparented_child() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--squash", "feature/lower"]);
+    run_git(repo.path(), &["commit", "-m", "lower (squashed)"]);
+    write_and_commit(&repo, "upper.txt", "main\n", "main edits upper.txt");
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+    let upper = rev_parse(repo.path(), "feature/upper");
+
+    pk_cmd()
+        .args(["sync"])
+        .current_dir(repo.path())
+        .assert()
+        .failure()
+        .stdout(contains("Reparented 'feature/upper' onto 'main'"));
+    assert_eq!(stack_parent(repo.path(), "feature/upper"), "main");
+
+    pk_cmd()
+        .args(["sync", "--abort"])
+        .current_dir(repo.path())
+        .assert()
+        .success();
+
+    assert_eq!(stack_parent(repo.path(), "feature/upper"), "feature/lower");
+    assert_eq!(rev_parse(repo.path(), "feature/upper"), upper);
+}
+
+#[test]
+fn sync_delete_merged_removes_merged_branch() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+
+    run_git(repo.path(), &["checkout", "main"]);
+    run_git(repo.path(), &["merge", "--no-ff", "-m", "merge lower", "feature/lower"]);
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+
+    pk_cmd()
+        .args(["sync", "--delete-merged"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Branch 'feature/lower' is merged into 'main'"))
+        .stdout(contains("Deleted merged branch 'feature/lower'"));
+
+    assert!(!git_output(repo.path(), &["branch", "--list", "feature/lower"]).contains("lower"));
+    let stacks = fs::read_to_string(repo.path().join(".pancake/stacks.json")).expect("read stacks");
+    assert!(!stacks.contains("feature/lower"));
+    assert_eq!(stack_parent(repo.path(), "feature/upper"), "main");
+    assert_eq!(
+        git_output(repo.path(), &["log", "--format=%s", "main..feature/upper"]),
+        "upper commit\n"
+    );
+}
+
+#[test]
+fn sync_detects_cherry_picked_parent_by_patch_id() {
+    let repo = TestRepo::new("main");
+    init_pk(&repo);
+    create_two_branch_stack(&repo);
+    let lower_commit = rev_parse(repo.path(), "feature/lower");
+
+    // Later trunk changes to the same lines leave only the patch id to match
+    run_git(repo.path(), &["checkout", "main"]);
+    write_and_commit(&repo, "README.md", "main moved on", "main update");
+    run_git(repo.path(), &["cherry-pick", &lower_commit]);
+    write_and_commit(&repo, "lower.txt", "one, revised\n", "revise lower");
+    run_git(repo.path(), &["checkout", "feature/upper"]);
+
+    pk_cmd()
+        .args(["sync"])
+        .current_dir(repo.path())
+        .assert()
+        .success()
+        .stdout(contains("Branch 'feature/lower' is merged into 'main'"));
+
+    assert_eq!(stack_parent(repo.path(), "feature/upper"), "main");
+    assert_eq!(
+        fs::read_to_string(repo.path().join("lower.txt")).expect("read file"),
+        "one, revised\n"
+    );
+}
+
 struct TestRepo {
     dir: TempDir,
 }
@@ -219,6 +738,32 @@ fn rev_parse(dir: &Path, rev: &str) -> String {
     String::from_utf8_lossy(&output.stdout).trim().to_string()
 }
 
+fn git_output(dir: &Path, args: &[&str]) -> String {
+    let output = StdCommand::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .unwrap_or_else(|err| panic!("failed to run git {:?}: {err}", args));
+    assert!(output.status.success(), "git {:?} failed", args);
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+fn stack_parent(dir: &Path, branch: &str) -> String {
+    let raw = fs::read_to_string(dir.join(".pancake/stacks.json")).expect("read stacks");
+    let doc: serde_json::Value = serde_json::from_str(&raw).expect("parse stacks");
+    doc["branches"][branch]["parent"]
+        .as_str()
+        .expect("parent")
+        .to_string()
+}
+
+fn reflog_checkouts(dir: &Path) -> usize {
+    git_output(dir, &["reflog", "--format=%gs", "HEAD"])
+        .lines()
+        .filter(|line| line.starts_with("checkout:"))
+        .count()
+}
+
 fn current_branch(dir: &Path) -> String {
     let output = StdCommand::new("git")
         .args(["rev-parse", "--abbrev-ref", "HEAD"])